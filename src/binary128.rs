@@ -0,0 +1,699 @@
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Neg, Add, Sub, Mul, Div, Rem};
+
+use num_traits::{Num, Zero, One, Bounded};
+use float_traits::*;
+
+use roundops::rmode;
+use impl_session_scalar;
+use flags::{ExceptionFlags, ExceptionStatus};
+
+const SIGN: u128 = 1 << 127;
+const EXP_MASK: u128 = 0x7FFF << 112;
+const FRAC_MASK: u128 = (1 << 112) - 1;
+const HIDDEN: u128 = 1 << 112;
+const BIAS: i32 = 16383;
+const EXP_MAX: i32 = 0x7FFF;
+
+/// Rounding direction of the software floating-point arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    ToNearest,
+    Upward,
+    Downward,
+    TowardZero,
+}
+
+thread_local! {
+    static ROUNDING: Cell<Rounding> = const { Cell::new(Rounding::ToNearest) };
+}
+
+thread_local! {
+    static FLAGS: Cell<ExceptionFlags> = const { Cell::new(ExceptionFlags::empty()) };
+}
+
+fn current() -> Rounding {
+    ROUNDING.with(|r| r.get())
+}
+
+fn raise(flags: ExceptionFlags) {
+    FLAGS.with(|f| f.set(f.get() | flags));
+}
+
+fn invalid() -> F128 {
+    raise(ExceptionFlags::INVALID);
+    F128::NAN
+}
+
+/// IEEE 754 binary128 number implemented in software.
+///
+/// The arithmetic operators round in the direction of the current thread's software
+/// rounding mode, which is edited through `rmode::EditRoundingMode` like the
+/// hardware one, so `Hardware<F128>` is a valid rounding method.
+/// Every result is correctly rounded, and `*_round` take the direction explicitly.
+#[derive(Clone, Copy)]
+pub struct F128(u128);
+
+// Rounds `sig / 2^126 * 2^(exp - BIAS)` with `sig` treated as sticky in its lowest bit.
+fn pack(sign: bool, exp: i32, sig: u128, mode: Rounding) -> F128 {
+    let sign_bit = if sign { SIGN } else { 0 };
+    if sig == 0 {
+        return F128(sign_bit);
+    }
+    let shift = sig.leading_zeros() as i32 - 1;
+    let (mut exp, mut sig) = if shift >= 0 {
+        (exp - shift, sig << shift)
+    } else {
+        (exp + 1, (sig >> 1) | (sig & 1))
+    };
+    if exp >= EXP_MAX {
+        return overflow(sign, mode);
+    }
+    if exp <= 0 {
+        let shift = 1 - exp;
+        sig = if shift >= 127 {
+            1
+        } else {
+            (sig >> shift) | (sig & ((1 << shift) - 1) != 0) as u128
+        };
+        exp = 0;
+    }
+
+    let (mant, rest) = (sig >> 14, sig & 0x3FFF);
+    if rest != 0 {
+        raise(ExceptionFlags::INEXACT);
+    }
+    let inc = match mode {
+        Rounding::ToNearest => rest > 0x2000 || (rest == 0x2000 && mant & 1 == 1),
+        Rounding::Upward => !sign && rest != 0,
+        Rounding::Downward => sign && rest != 0,
+        Rounding::TowardZero => false,
+    };
+    let mant = mant + inc as u128;
+    // A carry out of the significand increments the exponent field by itself.
+    let bits = if exp == 0 {
+        mant
+    } else {
+        (((exp - 1) as u128) << 112) + mant
+    };
+    if bits >= EXP_MASK {
+        overflow(sign, mode)
+    } else {
+        if bits < HIDDEN && rest != 0 {
+            raise(ExceptionFlags::UNDERFLOW);
+        }
+        F128(bits | sign_bit)
+    }
+}
+
+fn overflow(sign: bool, mode: Rounding) -> F128 {
+    raise(ExceptionFlags::OVERFLOW | ExceptionFlags::INEXACT);
+    let to_inf = match mode {
+        Rounding::ToNearest => true,
+        Rounding::Upward => !sign,
+        Rounding::Downward => sign,
+        Rounding::TowardZero => false,
+    };
+    let abs = if to_inf { F128::INFINITY } else { F128::MAX };
+    if sign { -abs } else { abs }
+}
+
+// Value of a finite nonzero number as `sig / 2^112 * 2^(exp - BIAS)`, `2^112 <= sig < 2^113`.
+fn unpack(x: F128) -> (bool, i32, u128) {
+    let (sign, exp, frac) = (x.0 & SIGN != 0, ((x.0 & EXP_MASK) >> 112) as i32, x.0 & FRAC_MASK);
+    if exp == 0 {
+        let shift = frac.leading_zeros() as i32 - 15;
+        (sign, 1 - shift, frac << shift)
+    } else {
+        (sign, exp, frac | HIDDEN)
+    }
+}
+
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let (a0, a1) = (a as u64 as u128, a >> 64);
+    let (b0, b1) = (b as u64 as u128, b >> 64);
+    let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+    let mid = (p00 >> 64) + (p01 as u64 as u128) + (p10 as u64 as u128);
+    (p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64), (p00 as u64 as u128) | (mid << 64))
+}
+
+impl F128 {
+    pub const ZERO: F128 = F128(0);
+    pub const ONE: F128 = F128((BIAS as u128) << 112);
+    pub const MAX: F128 = F128(EXP_MASK - 1);
+    pub const MIN_POSITIVE: F128 = F128(HIDDEN);
+    pub const EPSILON: F128 = F128(((BIAS - 112) as u128) << 112);
+    pub const INFINITY: F128 = F128(EXP_MASK);
+    pub const NAN: F128 = F128(EXP_MASK | (1 << 111));
+
+    pub fn from_bits(bits: u128) -> Self {
+        F128(bits)
+    }
+
+    pub fn to_bits(self) -> u128 {
+        self.0
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.0 & !SIGN > EXP_MASK
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.0 & EXP_MASK != EXP_MASK
+    }
+
+    pub fn is_sign_negative(self) -> bool {
+        self.0 & SIGN != 0
+    }
+
+    fn is_zero_bits(self) -> bool {
+        self.0 & !SIGN == 0
+    }
+
+    fn is_inf(self) -> bool {
+        self.0 & !SIGN == EXP_MASK
+    }
+
+    /// Nearest `f64`, regardless of the current rounding mode.
+    pub fn to_f64(self) -> f64 {
+        let sign = (self.0 >> 64) as u64 & (1 << 63);
+        if self.is_nan() {
+            return f64::NAN;
+        } else if self.is_inf() {
+            return f64::from_bits(sign | 0x7FF << 52);
+        } else if self.is_zero_bits() {
+            return f64::from_bits(sign);
+        }
+        let (_, exp, sig) = unpack(self);
+        let e = exp - BIAS;
+        if e > 1023 {
+            return f64::from_bits(sign | 0x7FF << 52);
+        }
+        let shift = if e < -1022 { 60 - 1022 - e } else { 60 };
+        if shift > 120 {
+            return f64::from_bits(sign);
+        }
+        let (q, rest, half) = (sig >> shift, sig & ((1 << shift) - 1), 1u128 << (shift - 1));
+        let q = (q + (rest > half || (rest == half && q & 1 == 1)) as u128) as u64;
+        let bits = if e < -1022 { q } else { (((e + 1022) as u64) << 52) + q };
+        f64::from_bits(sign | bits.min(0x7FF << 52))
+    }
+
+    pub fn add_round(self, rhs: F128, mode: Rounding) -> F128 {
+        if self.is_nan() || rhs.is_nan() {
+            return F128::NAN;
+        }
+        match (self.is_inf(), rhs.is_inf()) {
+            (true, true) if (self.0 ^ rhs.0) & SIGN != 0 => return invalid(),
+            (true, _) => return self,
+            (_, true) => return rhs,
+            _ => (),
+        }
+        match (self.is_zero_bits(), rhs.is_zero_bits()) {
+            (true, true) if (self.0 ^ rhs.0) & SIGN != 0 => {
+                return F128(if mode == Rounding::Downward { SIGN } else { 0 });
+            }
+            (true, _) => return rhs,
+            (_, true) => return self,
+            _ => (),
+        }
+
+        let (a, b) = if self.0 & !SIGN >= rhs.0 & !SIGN {
+            (unpack(self), unpack(rhs))
+        } else {
+            (unpack(rhs), unpack(self))
+        };
+        let (ma, mut mb) = (a.2 << 13, b.2 << 13);
+        let d = a.1 - b.1;
+        mb = if d >= 127 {
+            1
+        } else {
+            (mb >> d) | (mb & ((1 << d) - 1) != 0) as u128
+        };
+        if a.0 == b.0 {
+            pack(a.0, a.1 + 1, ma + mb, mode)
+        } else if ma == mb {
+            F128(if mode == Rounding::Downward { SIGN } else { 0 })
+        } else {
+            pack(a.0, a.1 + 1, ma - mb, mode)
+        }
+    }
+
+    pub fn sub_round(self, rhs: F128, mode: Rounding) -> F128 {
+        self.add_round(-rhs, mode)
+    }
+
+    pub fn mul_round(self, rhs: F128, mode: Rounding) -> F128 {
+        let sign = (self.0 ^ rhs.0) & SIGN != 0;
+        if self.is_nan() || rhs.is_nan() {
+            return F128::NAN;
+        } else if self.is_inf() || rhs.is_inf() {
+            if self.is_zero_bits() || rhs.is_zero_bits() {
+                return invalid();
+            }
+            return F128(EXP_MASK | (self.0 ^ rhs.0) & SIGN);
+        } else if self.is_zero_bits() || rhs.is_zero_bits() {
+            return F128((self.0 ^ rhs.0) & SIGN);
+        }
+        let (a, b) = (unpack(self), unpack(rhs));
+        let (hi, lo) = mul_wide(a.2, b.2);
+        let sig = (hi << 30) | (lo >> 98) | (lo & ((1 << 98) - 1) != 0) as u128;
+        pack(sign, a.1 + b.1 - BIAS, sig, mode)
+    }
+
+    pub fn div_round(self, rhs: F128, mode: Rounding) -> F128 {
+        let sign = (self.0 ^ rhs.0) & SIGN != 0;
+        if self.is_nan() || rhs.is_nan() {
+            return F128::NAN;
+        } else if (self.is_inf() && rhs.is_inf()) || (self.is_zero_bits() && rhs.is_zero_bits()) {
+            return invalid();
+        } else if self.is_inf() || rhs.is_zero_bits() {
+            if rhs.is_zero_bits() {
+                raise(ExceptionFlags::DIVIDE_BY_ZERO);
+            }
+            return F128(EXP_MASK | (self.0 ^ rhs.0) & SIGN);
+        } else if self.is_zero_bits() || rhs.is_inf() {
+            return F128((self.0 ^ rhs.0) & SIGN);
+        }
+        let (a, b) = (unpack(self), unpack(rhs));
+        let (mut q, mut r) = if a.2 >= b.2 { (1, a.2 - b.2) } else { (0, a.2) };
+        for _ in 0..126 {
+            q <<= 1;
+            r <<= 1;
+            if r >= b.2 {
+                r -= b.2;
+                q |= 1;
+            }
+        }
+        pack(sign, a.1 - b.1 + BIAS, q | (r != 0) as u128, mode)
+    }
+
+    pub fn sqrt_round(self, mode: Rounding) -> F128 {
+        if self.is_nan() {
+            return F128::NAN;
+        } else if self.is_sign_negative() && !self.is_zero_bits() {
+            return invalid();
+        } else if self.is_inf() || self.is_zero_bits() {
+            return self;
+        }
+        let (_, exp, mut m) = unpack(self);
+        let mut e = exp - BIAS;
+        if e & 1 != 0 {
+            m <<= 1;
+            e -= 1;
+        }
+        // Digit-by-digit square root of `m * 2^136`, which lies in [2^248, 2^250).
+        let (mut root, mut rem) = (0u128, 0u128);
+        for i in (0..125).rev() {
+            let pair = if 2 * i >= 136 { (m >> (2 * i - 136)) & 3 } else { 0 };
+            rem = (rem << 2) | pair;
+            let trial = (root << 2) | 1;
+            if rem >= trial {
+                rem -= trial;
+                root = (root << 1) | 1;
+            } else {
+                root <<= 1;
+            }
+        }
+        pack(false, e / 2 + BIAS, (root << 2) | (rem != 0) as u128, mode)
+    }
+
+    fn fmod(self, rhs: F128) -> F128 {
+        if self.is_nan() || rhs.is_nan() {
+            return F128::NAN;
+        } else if self.is_inf() || rhs.is_zero_bits() {
+            return invalid();
+        } else if rhs.is_inf() || self.is_zero_bits() || self.0 & !SIGN < rhs.0 & !SIGN {
+            return self;
+        }
+        let (a, b) = (unpack(self), unpack(rhs));
+        let mut r = a.2;
+        for _ in 0..(a.1 - b.1) {
+            if r >= b.2 {
+                r -= b.2;
+            }
+            r <<= 1;
+        }
+        if r >= b.2 {
+            r -= b.2;
+        }
+        pack(a.0, b.1, r << 14, Rounding::ToNearest)
+    }
+
+    fn order_key(self) -> i128 {
+        if self.is_sign_negative() {
+            -((self.0 & !SIGN) as i128)
+        } else {
+            self.0 as i128
+        }
+    }
+}
+
+impl From<f64> for F128 {
+    fn from(x: f64) -> Self {
+        let bits = x.to_bits();
+        let sign = bits >> 63 == 1;
+        let (exp, frac) = (((bits >> 52) & 0x7FF) as i32, (bits & ((1 << 52) - 1)) as u128);
+        if exp == 0x7FF {
+            if frac != 0 {
+                F128::NAN
+            } else if sign {
+                -F128::INFINITY
+            } else {
+                F128::INFINITY
+            }
+        } else if exp == 0 {
+            pack(sign, 1 - 1023 + BIAS, frac << 74, Rounding::ToNearest)
+        } else {
+            pack(sign, exp - 1023 + BIAS, (frac | 1 << 52) << 74, Rounding::ToNearest)
+        }
+    }
+}
+
+impl From<f32> for F128 {
+    fn from(x: f32) -> Self {
+        F128::from(x as f64)
+    }
+}
+
+impl PartialEq for F128 {
+    fn eq(&self, other: &F128) -> bool {
+        !self.is_nan() && !other.is_nan() && self.order_key() == other.order_key()
+    }
+}
+
+impl PartialOrd for F128 {
+    fn partial_cmp(&self, other: &F128) -> Option<Ordering> {
+        if self.is_nan() || other.is_nan() {
+            None
+        } else {
+            Some(self.order_key().cmp(&other.order_key()))
+        }
+    }
+}
+
+impl fmt::Debug for F128 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "F128({:#034x} ~ {:e})", self.0, self.to_f64())
+    }
+}
+
+impl Neg for F128 {
+    type Output = F128;
+    fn neg(self) -> F128 {
+        F128(self.0 ^ SIGN)
+    }
+}
+
+impl Add for F128 {
+    type Output = F128;
+    fn add(self, rhs: F128) -> F128 {
+        self.add_round(rhs, current())
+    }
+}
+
+impl Sub for F128 {
+    type Output = F128;
+    fn sub(self, rhs: F128) -> F128 {
+        self.sub_round(rhs, current())
+    }
+}
+
+impl Mul for F128 {
+    type Output = F128;
+    fn mul(self, rhs: F128) -> F128 {
+        self.mul_round(rhs, current())
+    }
+}
+
+impl Div for F128 {
+    type Output = F128;
+    fn div(self, rhs: F128) -> F128 {
+        self.div_round(rhs, current())
+    }
+}
+
+/// Remainder of the division truncated toward zero, which is always exact.
+impl Rem for F128 {
+    type Output = F128;
+    fn rem(self, rhs: F128) -> F128 {
+        self.fmod(rhs)
+    }
+}
+
+impl Zero for F128 {
+    fn zero() -> Self {
+        F128::ZERO
+    }
+    fn is_zero(&self) -> bool {
+        self.is_zero_bits()
+    }
+}
+
+impl One for F128 {
+    fn one() -> Self {
+        F128::ONE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseF128Error;
+
+/// Only integers are accepted. They are rounded to nearest.
+impl Num for F128 {
+    type FromStrRadixErr = ParseF128Error;
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseF128Error> {
+        let (sign, digits) = match s.as_bytes().first() {
+            Some(&b'-') => (true, &s[1..]),
+            Some(&b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() {
+            return Err(ParseF128Error);
+        }
+        let mut n = 0u128;
+        for c in digits.chars() {
+            let d = c.to_digit(radix).ok_or(ParseF128Error)?;
+            n = n.checked_mul(radix as u128)
+                .and_then(|n| n.checked_add(d as u128))
+                .ok_or(ParseF128Error)?;
+        }
+        Ok(pack(sign, 126 + BIAS, n, Rounding::ToNearest))
+    }
+}
+
+impl Bounded for F128 {
+    fn min_value() -> Self {
+        -F128::MAX
+    }
+    fn max_value() -> Self {
+        F128::MAX
+    }
+}
+
+impl Abs for F128 {
+    type Output = F128;
+    fn abs(&self) -> F128 {
+        F128(self.0 & !SIGN)
+    }
+    fn sign(&self) -> F128 {
+        if self.is_nan() {
+            F128::NAN
+        } else {
+            F128(F128::ONE.0 | self.0 & SIGN)
+        }
+    }
+}
+
+impl Sqrt for F128 {
+    type Output = F128;
+    fn sqrt(self) -> F128 {
+        self.sqrt_round(current())
+    }
+}
+
+impl BinaryFloat for F128 {
+    type Expo = i32;
+    fn bits() -> i32 {
+        113
+    }
+    fn eps() -> Self {
+        F128::EPSILON
+    }
+    fn two_powi(p: i32) -> Self {
+        pack(false, p.clamp(-2 * BIAS, 2 * BIAS) + BIAS, 1 << 126, Rounding::ToNearest)
+    }
+}
+
+impl BoundedFloat for F128 {
+    fn max_exponent() -> i32 {
+        BIAS
+    }
+    fn min_exponent() -> i32 {
+        1 - BIAS
+    }
+}
+
+impl Infinite for F128 {
+    fn infinity() -> Self {
+        F128::INFINITY
+    }
+    fn neg_infinity() -> Self {
+        -F128::INFINITY
+    }
+    fn is_infinite(&self) -> bool {
+        self.is_inf()
+    }
+}
+
+impl Underflow for F128 {
+    fn min_positive() -> Self {
+        F128::MIN_POSITIVE
+    }
+    fn unit_underflow() -> Self {
+        F128(1)
+    }
+}
+
+impl IEEE754Float for F128 {
+    fn nan() -> Self {
+        F128::NAN
+    }
+}
+
+impl ExceptionStatus for F128 {
+    fn exception_flags() -> ExceptionFlags {
+        FLAGS.with(|f| f.get())
+    }
+    fn clear_exception_flags() {
+        FLAGS.with(|f| f.set(ExceptionFlags::empty()))
+    }
+}
+
+impl rmode::EditRoundingMode for F128 {
+    type RoundingState = Rounding;
+
+    fn current_rounding_state() -> Rounding {
+        current()
+    }
+    unsafe fn set_rounding_state(state: Rounding) {
+        ROUNDING.with(|r| r.set(state))
+    }
+    unsafe fn upward() {
+        Self::set_rounding_state(Rounding::Upward)
+    }
+    unsafe fn downward() {
+        Self::set_rounding_state(Rounding::Downward)
+    }
+    unsafe fn to_nearest() {
+        Self::set_rounding_state(Rounding::ToNearest)
+    }
+    unsafe fn toward_zero() {
+        Self::set_rounding_state(Rounding::TowardZero)
+    }
+}
+
+impl_session_scalar!(F128);
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+    use num_traits::{Num, Bounded};
+    use float_traits::*;
+
+    use roundops::*;
+    use methods::{Hardware, EmulationRegular};
+
+    use super::{F128, Rounding};
+
+    fn random_f64<R: Rng>(rng: &mut R) -> f64 {
+        let x = f64::from_bits(rng.gen());
+        if x.is_nan() { rng.gen() } else { x }
+    }
+
+    fn random_f128<R: Rng>(rng: &mut R) -> F128 {
+        let exp = rng.gen_range(16383 - 1000, 16383 + 1000) as u128;
+        let frac = (rng.gen::<u64>() as u128) << 64 | rng.gen::<u64>() as u128;
+        let sign = if rng.gen() { 1 << 127 } else { 0 };
+        F128::from_bits(sign | exp << 112 | frac & ((1 << 112) - 1))
+    }
+
+    fn same(a: f64, b: f64) -> bool {
+        a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan())
+    }
+
+    #[test]
+    fn nearest_matches_f64() {
+        // Rounding to binary128 first is innocuous for binary64 inputs (113 >= 2 * 53 + 2).
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (random_f64(&mut rng), random_f64(&mut rng));
+            let (x, y) = (F128::from(a), F128::from(b));
+            assert_eq!(x.to_f64().to_bits(), a.to_bits());
+            assert!(same((x + y).to_f64(), a + b));
+            assert!(same((x - y).to_f64(), a - b));
+            assert!(same((x * y).to_f64(), a * b));
+            assert!(same((x / y).to_f64(), a / b));
+            assert!(same(x.abs().sqrt().to_f64(), a.abs().sqrt()));
+            assert!(same((x % y).to_f64(), a % b));
+        }
+    }
+
+    #[test]
+    fn software_mode() {
+        type HwF128 = Hardware<F128>;
+        type EmuF128 = EmulationRegular<F128>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b) = (random_f128(&mut rng), random_f128(&mut rng));
+            assert_eq!(HwF128::add_up(a, b).to_bits(), EmuF128::add_up(a, b).to_bits());
+            assert_eq!(HwF128::add_down(a, b).to_bits(), EmuF128::add_down(a, b).to_bits());
+            assert_eq!(HwF128::sub_up(a, b).to_bits(), EmuF128::sub_up(a, b).to_bits());
+            assert_eq!(HwF128::sub_down(a, b).to_bits(), EmuF128::sub_down(a, b).to_bits());
+            assert_eq!(HwF128::mul_up(a, b).to_bits(), EmuF128::mul_up(a, b).to_bits());
+            assert_eq!(HwF128::mul_down(a, b).to_bits(), EmuF128::mul_down(a, b).to_bits());
+            assert_eq!(HwF128::div_up(a, b).to_bits(), EmuF128::div_up(a, b).to_bits());
+            assert_eq!(HwF128::div_down(a, b).to_bits(), EmuF128::div_down(a, b).to_bits());
+            let a = a.abs();
+            assert_eq!(HwF128::sqrt_up(a).to_bits(), EmuF128::sqrt_up(a).to_bits());
+            assert_eq!(HwF128::sqrt_down(a).to_bits(), EmuF128::sqrt_down(a).to_bits());
+            let z = a.mul_round(b, Rounding::TowardZero);
+            assert!(z.abs() <= (a * b).abs() && (a * b).abs() <= z.abs() + z.abs() * F128::eps());
+        }
+    }
+
+    #[test]
+    fn special_values() {
+        let (one, three) = (F128::from(1.), F128::from(3.));
+        let (up, down) = (one.div_round(three, Rounding::Upward),
+                          one.div_round(three, Rounding::Downward));
+        assert_eq!(up.to_bits() - down.to_bits(), 1);
+        assert_eq!(down.to_bits(), one.div_round(three, Rounding::TowardZero).to_bits());
+
+        let max = F128::max_value();
+        assert!(max.add_round(max, Rounding::ToNearest).is_infinite());
+        assert!(max.add_round(max, Rounding::Upward).is_infinite());
+        assert!(max.add_round(max, Rounding::Downward) == max);
+        assert!(max.add_round(max, Rounding::TowardZero) == max);
+        assert!((-max).mul_round(max, Rounding::Upward) == -max);
+
+        let tiny = F128::unit_underflow();
+        assert!(tiny == F128::min_positive() * F128::eps());
+        assert!(tiny == F128::two_powi(-16494) && F128::two_powi(-16495) == F128::from(0.));
+        assert!(tiny.mul_round(F128::from(0.5), Rounding::Upward) == tiny);
+        assert!(tiny.mul_round(F128::from(0.5), Rounding::ToNearest).to_bits() == 0);
+        assert!(tiny.add_round(-tiny, Rounding::Downward).is_sign_negative());
+        assert!(F128::from(1e-310) + F128::from(1e-310) == F128::from(2e-310));
+
+        let two = F128::from(2.);
+        let (up, down) = (two.sqrt_round(Rounding::Upward), two.sqrt_round(Rounding::Downward));
+        assert_eq!(up.to_bits() - down.to_bits(), 1);
+        assert!(down * down < two && two < up * up);
+        assert!(F128::from(-0.).sqrt().is_sign_negative() && F128::from(-1.).sqrt().is_nan());
+
+        assert!(F128::from_str_radix("-ff", 16) == Ok(F128::from(-255.)));
+        assert!(F128::from_str_radix("1.5", 10).is_err());
+    }
+}
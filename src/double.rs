@@ -0,0 +1,495 @@
+use core::cmp::Ordering;
+use core::ops::{Neg, Add, Sub, Mul, Div};
+
+use num_traits::{Zero, One};
+use float_traits::IEEE754Float;
+
+use roundops::*;
+use impl_session_scalar;
+use methods::EmulationRegular;
+use expansion::Expansion;
+use utils::safeeft::{fasttwosum, safetwosum_branch as safetwosum,
+                     safetwoproduct_branch as safetwoproduct};
+
+/// Unevaluated sum `hi + lo` of two floats with `|lo| <= ulp(hi) / 2`.
+///
+/// It is its own rounding method: the directed operations round the exact result of
+/// the operation to a neighbouring double-word number, so `RoundedNum` and
+/// `RoundedSession` can be used with about twice the precision of `T`.
+#[derive(Clone, Copy, Debug)]
+pub struct Double<T> {
+    hi: T,
+    lo: T,
+}
+
+pub type DoubleDouble = Double<f64>;
+pub type DoubleFloat = Double<f32>;
+
+type Emu<T> = EmulationRegular<T>;
+
+fn is_finite<T: IEEE754Float>(x: &T) -> bool {
+    !x.is_infinite() && x.partial_cmp(x).is_some()
+}
+
+impl<T: IEEE754Float + Clone> Double<T> {
+    /// Exact sum of `hi` and `lo`, renormalized.
+    pub fn new(hi: T, lo: T) -> Self {
+        let (hi, lo) = safetwosum(hi, lo);
+        Double { hi, lo }
+    }
+
+    pub fn hi(&self) -> T {
+        self.hi.clone()
+    }
+
+    pub fn lo(&self) -> T {
+        self.lo.clone()
+    }
+
+    fn is_finite(&self) -> bool {
+        is_finite(&self.hi)
+    }
+
+    fn expansion(&self) -> Expansion<T> {
+        Expansion::from(self.lo.clone()) + self.hi.clone()
+    }
+
+    fn upper(&self) -> T {
+        Emu::<T>::add_up(self.hi.clone(), self.lo.clone())
+    }
+
+    fn lower(&self) -> T {
+        Emu::<T>::add_down(self.hi.clone(), self.lo.clone())
+    }
+
+    // Bound of `a * b` as an expansion. A partial product is exact only if its error is
+    // representable, so the smaller ones are rounded with `dir` instead.
+    fn product(a: &Self, b: &Self, dir: fn(T, T) -> T) -> Expansion<T> {
+        let tiny = T::radix() * T::radix() * T::min_positive() / T::eps();
+        let pairs = [(&a.hi, &b.hi), (&a.hi, &b.lo), (&a.lo, &b.hi), (&a.lo, &b.lo)];
+        pairs.iter().fold(Expansion::zero(), |e, &(x, y)| {
+            if *x == T::zero() || *y == T::zero() || (x.clone() * y.clone()).abs() >= tiny {
+                e + Expansion::from(x.clone()) * y.clone()
+            } else {
+                e + dir(x.clone(), y.clone())
+            }
+        })
+    }
+
+    fn product_up(a: &Self, b: &Self) -> Expansion<T> {
+        Self::product(a, b, Emu::<T>::mul_up)
+    }
+
+    fn product_down(a: &Self, b: &Self) -> Expansion<T> {
+        Self::product(a, b, Emu::<T>::mul_down)
+    }
+
+    fn round_up(e: Expansion<T>) -> Self {
+        let e = e.compress();
+        let hi = e.approx();
+        let lo = (e - hi.clone()).upper::<Emu<T>>();
+        Double::new(hi, lo)
+    }
+
+    fn round_down(e: Expansion<T>) -> Self {
+        let e = e.compress();
+        let hi = e.approx();
+        let lo = (e - hi.clone()).lower::<Emu<T>>();
+        Double::new(hi, lo)
+    }
+
+    // Result of an operation with finite operands whose rounding to nearest is not finite.
+    fn overflow_up(near: Self) -> Self {
+        if near.hi == T::neg_infinity() {
+            Double::from(T::min_value())
+        } else {
+            near
+        }
+    }
+
+    fn overflow_down(near: Self) -> Self {
+        if near.hi == T::infinity() {
+            Double::from(T::max_value())
+        } else {
+            near
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> From<T> for Double<T> {
+    fn from(x: T) -> Self {
+        Double { hi: x, lo: T::zero() }
+    }
+}
+
+impl<T: IEEE754Float + Clone> PartialEq for Double<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hi == other.hi && self.lo == other.lo
+    }
+}
+
+impl<T: IEEE754Float + Clone> PartialOrd for Double<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ord => ord,
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> Neg for Double<T> {
+    type Output = Double<T>;
+    fn neg(self) -> Double<T> {
+        Double {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> Add for Double<T> {
+    type Output = Double<T>;
+    fn add(self, rhs: Double<T>) -> Double<T> {
+        let (s1, s2) = safetwosum(self.hi, rhs.hi);
+        if !is_finite(&s1) {
+            return Double::from(s1);
+        }
+        let (t1, t2) = safetwosum(self.lo, rhs.lo);
+        let (s1, s2) = fasttwosum(s1, s2 + t1);
+        let (s1, s2) = fasttwosum(s1, s2 + t2);
+        Double { hi: s1, lo: s2 }
+    }
+}
+
+impl<T: IEEE754Float + Clone> Sub for Double<T> {
+    type Output = Double<T>;
+    fn sub(self, rhs: Double<T>) -> Double<T> {
+        self + (-rhs)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Mul for Double<T> {
+    type Output = Double<T>;
+    fn mul(self, rhs: Double<T>) -> Double<T> {
+        let (p1, p2) = safetwoproduct(self.hi.clone(), rhs.hi.clone());
+        if !is_finite(&p1) {
+            return Double::from(p1);
+        }
+        let p2 = p2 + (self.hi * rhs.lo + self.lo * rhs.hi);
+        let (p1, p2) = fasttwosum(p1, p2);
+        Double { hi: p1, lo: p2 }
+    }
+}
+
+impl<T: IEEE754Float + Clone> Div for Double<T> {
+    type Output = Double<T>;
+    fn div(self, rhs: Double<T>) -> Double<T> {
+        let q1 = self.hi.clone() / rhs.hi.clone();
+        if !is_finite(&q1) || !rhs.is_finite() {
+            return Double::from(q1);
+        }
+        let r = self - rhs.clone() * Double::from(q1.clone());
+        let q2 = r.hi.clone() / rhs.hi.clone();
+        let r = r - rhs.clone() * Double::from(q2.clone());
+        let q3 = r.hi / rhs.hi;
+        let (q1, q2) = fasttwosum(q1, q2);
+        Double { hi: q1, lo: q2 } + Double::from(q3)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Double<T> {
+    pub fn sqrt(self) -> Self {
+        if self.hi <= T::zero() || !self.is_finite() {
+            return Double::from(self.hi.sqrt());
+        }
+        let s = self.hi.clone().sqrt();
+        let (p1, p2) = safetwoproduct(s.clone(), s.clone());
+        let r = ((self.hi - p1) - p2 + self.lo) / (T::radix() * s.clone());
+        let (s, r) = fasttwosum(s, r);
+        Double { hi: s, lo: r }
+    }
+}
+
+impl<T: IEEE754Float + Clone> Zero for Double<T> {
+    fn zero() -> Self {
+        Double::from(T::zero())
+    }
+    fn is_zero(&self) -> bool {
+        self.hi == T::zero()
+    }
+}
+
+impl<T: IEEE754Float + Clone> One for Double<T> {
+    fn one() -> Self {
+        Double::from(T::one())
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundingMethod for Double<T> {
+    type HostMethod = rmode::DefaultRounding;
+    type Num = Double<T>;
+}
+
+impl<T: IEEE754Float + Clone> RoundAdd for Double<T> {
+    fn add_up(a: Self, b: Self) -> Self {
+        let near = a.clone() + b.clone();
+        if !(a.is_finite() && b.is_finite()) {
+            near
+        } else if !near.is_finite() {
+            Self::overflow_up(near)
+        } else {
+            Self::round_up(a.expansion() + b.expansion())
+        }
+    }
+    fn add_down(a: Self, b: Self) -> Self {
+        let near = a.clone() + b.clone();
+        if !(a.is_finite() && b.is_finite()) {
+            near
+        } else if !near.is_finite() {
+            Self::overflow_down(near)
+        } else {
+            Self::round_down(a.expansion() + b.expansion())
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundSub for Double<T> {
+    #[inline]
+    fn sub_up(a: Self, b: Self) -> Self {
+        Self::add_up(a, -b)
+    }
+    #[inline]
+    fn sub_down(a: Self, b: Self) -> Self {
+        Self::add_down(a, -b)
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundMul for Double<T> {
+    fn mul_up(a: Self, b: Self) -> Self {
+        let near = a.clone() * b.clone();
+        if !(a.is_finite() && b.is_finite()) {
+            near
+        } else if !near.is_finite() {
+            Self::overflow_up(near)
+        } else {
+            Self::round_up(Self::product_up(&a, &b))
+        }
+    }
+    fn mul_down(a: Self, b: Self) -> Self {
+        let near = a.clone() * b.clone();
+        if !(a.is_finite() && b.is_finite()) {
+            near
+        } else if !near.is_finite() {
+            Self::overflow_down(near)
+        } else {
+            Self::round_down(Self::product_down(&a, &b))
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundDiv for Double<T> {
+    fn div_up(a: Self, b: Self) -> Self {
+        let (a, b) = if b.hi < T::zero() { (-a, -b) } else { (a, b) };
+        let q = a.clone() / b.clone();
+        if !(a.is_finite() && b.is_finite()) || b.hi == T::zero() {
+            q
+        } else if !q.is_finite() {
+            Self::overflow_up(q)
+        } else {
+            // a / b = q + r / b with the residual r = a - q * b
+            let r = a.expansion() - Self::product_down(&q, &b);
+            let r_up = r.upper::<Emu<T>>();
+            let c = if r_up >= T::zero() {
+                Emu::<T>::div_up(r_up, b.lower())
+            } else {
+                Emu::<T>::div_up(r_up, b.upper())
+            };
+            Double::new(q.hi, Emu::<T>::add_up(q.lo, c))
+        }
+    }
+    fn div_down(a: Self, b: Self) -> Self {
+        let (a, b) = if b.hi < T::zero() { (-a, -b) } else { (a, b) };
+        let q = a.clone() / b.clone();
+        if !(a.is_finite() && b.is_finite()) || b.hi == T::zero() {
+            q
+        } else if !q.is_finite() {
+            Self::overflow_down(q)
+        } else {
+            let r = a.expansion() - Self::product_up(&q, &b);
+            let r_down = r.lower::<Emu<T>>();
+            let c = if r_down >= T::zero() {
+                Emu::<T>::div_down(r_down, b.upper())
+            } else {
+                Emu::<T>::div_down(r_down, b.lower())
+            };
+            Double::new(q.hi, Emu::<T>::add_down(q.lo, c))
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundSqrt for Double<T> {
+    fn sqrt_up(a: Self) -> Self {
+        let s = a.clone().sqrt();
+        if a.hi <= T::zero() || !a.is_finite() {
+            return s;
+        }
+        let d = Self::product_down(&s, &s) - a.expansion();
+        if d.sign() != Ordering::Less {
+            s
+        } else {
+            // sqrt(a) - s = (a - s^2) / (sqrt(a) + s) <= (a - s^2) / 2s
+            let c = Emu::<T>::div_up((-d).upper::<Emu<T>>(),
+                                     Emu::<T>::mul_down(T::radix(), s.lower()));
+            Double::new(s.hi, Emu::<T>::add_up(s.lo, c))
+        }
+    }
+    fn sqrt_down(a: Self) -> Self {
+        let s = a.clone().sqrt();
+        if a.hi <= T::zero() || !a.is_finite() {
+            return s;
+        }
+        let d = Self::product_up(&s, &s) - a.expansion();
+        if d.sign() != Ordering::Greater {
+            s
+        } else {
+            // s - sqrt(a) = (s^2 - a) / (s + sqrt(a)) <= (s^2 - a) / s
+            let c = Emu::<T>::div_up(d.upper::<Emu<T>>(), s.lower());
+            Double::new(s.hi, Emu::<T>::sub_down(s.lo, c))
+        }
+    }
+}
+
+impl<T: IEEE754Float + Clone> RoundedSession for Double<T> {
+    type Num = Double<T>;
+}
+
+impl_session_scalar!(<T> Double<T>);
+
+#[cfg(test)]
+mod tests {
+    use core::cmp::Ordering;
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use expansion::Expansion;
+
+    use super::DoubleDouble;
+
+    fn random<R: Rng>(rng: &mut R) -> DoubleDouble {
+        let hi = (rng.gen::<f64>() - 0.5) * 2f64.powi(rng.gen_range(-60, 60));
+        DoubleDouble::new(hi, hi * (rng.gen::<f64>() - 0.5) * 2f64.powi(-53))
+    }
+
+    fn exact(x: &DoubleDouble) -> Expansion<f64> {
+        Expansion::from(x.lo()) + x.hi()
+    }
+
+    fn assert_bounds(up: DoubleDouble, down: DoubleDouble, e: Expansion<f64>) {
+        assert_ne!((exact(&up) - e.clone()).sign(), Ordering::Less);
+        assert_ne!((e.clone() - exact(&down)).sign(), Ordering::Less);
+        let width = (exact(&up) - exact(&down)).approx();
+        assert!(width <= e.approx().abs() * 2f64.powi(-100));
+    }
+
+    #[test]
+    fn addition() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let (x, y) = (DoubleDouble::add_up(a, b), DoubleDouble::add_down(a, b));
+            assert_bounds(x, y, exact(&a) + exact(&b));
+            let (x, y) = (DoubleDouble::sub_up(a, b), DoubleDouble::sub_down(a, b));
+            assert_bounds(x, y, exact(&a) - exact(&b));
+        }
+    }
+
+    #[test]
+    fn multiplication() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let (x, y) = (DoubleDouble::mul_up(a, b), DoubleDouble::mul_down(a, b));
+            assert_bounds(x, y, exact(&a) * exact(&b));
+        }
+    }
+
+    #[test]
+    fn division() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let (x, y) = (DoubleDouble::div_up(a, b), DoubleDouble::div_down(a, b));
+            // x * b >= a >= y * b for b > 0, reversed for b < 0
+            let (hi, lo) = (exact(&x) * exact(&b), exact(&y) * exact(&b));
+            let (hi, lo) = if b.hi() > 0. { (hi, lo) } else { (lo, hi) };
+            assert_ne!((hi - exact(&a)).sign(), Ordering::Less);
+            assert_ne!((exact(&a) - lo).sign(), Ordering::Less);
+            assert!(DoubleDouble::sub_up(x, y).hi() <= (x.hi() * 2f64.powi(-100)).abs());
+        }
+    }
+
+    #[test]
+    fn sqrt() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let a = random(&mut rng);
+            let a = if a.hi() < 0. { -a } else { a };
+            let (x, y) = (DoubleDouble::sqrt_up(a), DoubleDouble::sqrt_down(a));
+            assert_ne!((exact(&x) * exact(&x) - exact(&a)).sign(), Ordering::Less);
+            assert_ne!((exact(&a) - exact(&y) * exact(&y)).sign(), Ordering::Less);
+            assert!(DoubleDouble::sub_up(x, y).hi() <= x.hi() * 2f64.powi(-100));
+        }
+    }
+
+    #[test]
+    fn underflow() {
+        // Scaling by 2^700 keeps every product of the checks exact.
+        fn scaled(x: &DoubleDouble) -> Expansion<f64> {
+            Expansion::from(x.lo() * 2f64.powi(700)) + x.hi() * 2f64.powi(700)
+        }
+        fn tiny<R: Rng>(rng: &mut R) -> DoubleDouble {
+            let hi = (rng.gen::<f64>() - 0.5) * 2f64.powi(rng.gen_range(-1060, -480));
+            DoubleDouble::new(hi, hi * (rng.gen::<f64>() - 0.5) * 2f64.powi(-53))
+        }
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b, c) = (tiny(&mut rng), tiny(&mut rng), random(&mut rng));
+            let (x, y) = (DoubleDouble::mul_up(a, b), DoubleDouble::mul_down(a, b));
+            let e = scaled(&a) * scaled(&b);
+            assert_ne!((scaled(&x) * 2f64.powi(700) - e.clone()).sign(), Ordering::Less);
+            assert_ne!((e - scaled(&y) * 2f64.powi(700)).sign(), Ordering::Less);
+
+            let (x, y) = (DoubleDouble::div_up(a, c), DoubleDouble::div_down(a, c));
+            let (hi, lo) = (scaled(&x) * exact(&c), scaled(&y) * exact(&c));
+            let (hi, lo) = if c.hi() > 0. { (hi, lo) } else { (lo, hi) };
+            assert_ne!((hi - scaled(&a)).sign(), Ordering::Less);
+            assert_ne!((scaled(&a) - lo).sign(), Ordering::Less);
+
+            let a = if a.hi() < 0. { -a } else { a };
+            let (x, y) = (DoubleDouble::sqrt_up(a), DoubleDouble::sqrt_down(a));
+            let e = scaled(&a) * 2f64.powi(700);
+            assert_ne!((scaled(&x) * scaled(&x) - e.clone()).sign(), Ordering::Less);
+            assert_ne!((e - scaled(&y) * scaled(&y)).sign(), Ordering::Less);
+        }
+        let t = DoubleDouble::from(1e-200);
+        assert_eq!(DoubleDouble::mul_up(t, t).hi(), 5e-324);
+        assert_eq!(DoubleDouble::mul_down(t, t).hi(), 0.);
+    }
+
+    #[test]
+    fn rounded_num() {
+        type Up = RoundedNum<direction::Upward, DoubleDouble, DoubleDouble>;
+        type Down = RoundedNum<direction::Downward, DoubleDouble, DoubleDouble>;
+        let third = |x: f64| DoubleDouble::from(1.) / DoubleDouble::from(x);
+        let up = (Up::new(DoubleDouble::from(1.)) / Up::new(DoubleDouble::from(3.))).extract();
+        let down = (Down::new(DoubleDouble::from(1.)) / Down::new(DoubleDouble::from(3.)))
+            .extract();
+        assert!(down < up && down <= third(3.) && third(3.) <= up);
+        assert_eq!(DoubleDouble::add_up(DoubleDouble::from(1e308), DoubleDouble::from(1e308))
+                       .hi(),
+                   f64::INFINITY);
+        assert_eq!(DoubleDouble::add_down(DoubleDouble::from(1e308), DoubleDouble::from(1e308))
+                       .hi(),
+                   f64::MAX);
+    }
+}
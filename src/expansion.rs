@@ -0,0 +1,256 @@
+use core::cmp::Ordering;
+use core::ops::{Neg, Add, Sub, Mul};
+
+use float_traits::IEEE754Float;
+
+use roundops::*;
+use utils::safeeft::{safetwosum_branch as safetwosum, safetwoproduct_branch as safetwoproduct};
+
+/// Exact sum of nonoverlapping floating-point components.
+///
+/// Components are kept in increasing order of magnitude and zeros are eliminated.
+/// The algorithms follow J. R. Shewchuk, "Adaptive Precision Floating-Point Arithmetic
+/// and Fast Robust Geometric Predicates", and assume rounding to nearest without
+/// overflow or underflow of the components.
+#[derive(Clone, Debug)]
+pub struct Expansion<T>(Vec<T>);
+
+impl<T: IEEE754Float + Clone> Expansion<T> {
+    pub fn zero() -> Self {
+        Expansion(Vec::new())
+    }
+
+    pub fn components(&self) -> &[T] {
+        &self.0
+    }
+
+    pub fn sign(&self) -> Ordering {
+        match self.0.last() {
+            Some(x) if *x > T::zero() => Ordering::Greater,
+            Some(x) if *x < T::zero() => Ordering::Less,
+            _ => Ordering::Equal,
+        }
+    }
+
+    /// Approximation of the value with a relative error of about one ulp after `compress`.
+    pub fn approx(&self) -> T {
+        self.0.iter().fold(T::zero(), |acc, e| acc + e.clone())
+    }
+
+    pub fn upper<M: RoundAdd<Num = T>>(&self) -> T {
+        self.0.iter().fold(T::zero(), |acc, e| M::add_up(acc, e.clone()))
+    }
+
+    pub fn lower<M: RoundAdd<Num = T>>(&self) -> T {
+        self.0.iter().fold(T::zero(), |acc, e| M::add_down(acc, e.clone()))
+    }
+
+    /// Renormalizes to an equivalent expansion with fewer, larger components,
+    /// whose largest component approximates the whole value.
+    pub fn compress(self) -> Self {
+        let mut g = Vec::with_capacity(self.0.len());
+        let mut rest = self.0.into_iter().rev();
+        let mut q = match rest.next() {
+            Some(e) => e,
+            None => return Expansion::zero(),
+        };
+        for e in rest {
+            let (s, r) = safetwosum(q, e);
+            if r != T::zero() {
+                g.push(s);
+                q = r;
+            } else {
+                q = s;
+            }
+        }
+        g.push(q);
+
+        let mut h = Vec::with_capacity(g.len());
+        let mut rest = g.into_iter().rev();
+        let mut q = rest.next().unwrap();
+        for e in rest {
+            let (s, r) = safetwosum(e, q);
+            if r != T::zero() {
+                h.push(r);
+            }
+            q = s;
+        }
+        if q != T::zero() {
+            h.push(q);
+        }
+        Expansion(h)
+    }
+
+    fn grow(self, b: T) -> Self {
+        let mut h = Vec::with_capacity(self.0.len() + 1);
+        let mut q = b;
+        for e in self.0 {
+            let (s, r) = safetwosum(q, e);
+            if r != T::zero() {
+                h.push(r);
+            }
+            q = s;
+        }
+        if q != T::zero() {
+            h.push(q);
+        }
+        Expansion(h)
+    }
+
+    fn scale(&self, b: T) -> Self {
+        let mut h = Vec::with_capacity(2 * self.0.len());
+        let mut components = self.0.iter().cloned();
+        let mut q = match components.next() {
+            Some(e) => {
+                let (p, r) = safetwoproduct(e, b.clone());
+                if r != T::zero() {
+                    h.push(r);
+                }
+                p
+            }
+            None => return Expansion::zero(),
+        };
+        for e in components {
+            let (p_h, p_l) = safetwoproduct(e, b.clone());
+            let (s, r) = safetwosum(q, p_l);
+            if r != T::zero() {
+                h.push(r);
+            }
+            let (s, r) = safetwosum(p_h, s);
+            if r != T::zero() {
+                h.push(r);
+            }
+            q = s;
+        }
+        if q != T::zero() {
+            h.push(q);
+        }
+        Expansion(h)
+    }
+}
+
+impl<T: IEEE754Float + Clone> From<T> for Expansion<T> {
+    fn from(x: T) -> Self {
+        Expansion::zero().grow(x)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Neg for Expansion<T> {
+    type Output = Expansion<T>;
+    fn neg(self) -> Expansion<T> {
+        Expansion(self.0.into_iter().map(|e| -e).collect())
+    }
+}
+
+impl<T: IEEE754Float + Clone> Add for Expansion<T> {
+    type Output = Expansion<T>;
+    fn add(self, rhs: Expansion<T>) -> Expansion<T> {
+        let (long, short) = if self.0.len() >= rhs.0.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        short.0.into_iter().fold(long, |acc, e| acc.grow(e))
+    }
+}
+
+impl<T: IEEE754Float + Clone> Add<T> for Expansion<T> {
+    type Output = Expansion<T>;
+    fn add(self, rhs: T) -> Expansion<T> {
+        self.grow(rhs)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Sub<T> for Expansion<T> {
+    type Output = Expansion<T>;
+    fn sub(self, rhs: T) -> Expansion<T> {
+        self.grow(-rhs)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Mul<T> for Expansion<T> {
+    type Output = Expansion<T>;
+    fn mul(self, rhs: T) -> Expansion<T> {
+        self.scale(rhs)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Sub for Expansion<T> {
+    type Output = Expansion<T>;
+    fn sub(self, rhs: Expansion<T>) -> Expansion<T> {
+        self + (-rhs)
+    }
+}
+
+impl<T: IEEE754Float + Clone> Mul for Expansion<T> {
+    type Output = Expansion<T>;
+    fn mul(self, rhs: Expansion<T>) -> Expansion<T> {
+        let (long, short) = if self.0.len() >= rhs.0.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        short.0.into_iter().fold(Expansion::zero(), |acc, e| Add::add(acc, long.scale(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cmp::Ordering;
+    use rand::{Rng, thread_rng};
+
+    use methods::EmulationRegular;
+    use utils::FloatSuccPred;
+
+    use super::Expansion;
+
+    type Emuf64 = EmulationRegular<f64>;
+
+    #[test]
+    fn cancellation() {
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let v = (0..rng.gen_range(1, 30))
+                .map(|_| (rng.gen::<f64>() - 0.5) * 2f64.powi(rng.gen_range(-500, 500)))
+                .collect::<Vec<_>>();
+            let mut e = v.iter().fold(Expansion::zero(), |acc, &x| acc + x);
+            let tiny = rng.gen::<f64>() * 2f64.powi(-600);
+            e = e + tiny;
+            for &x in v.iter().rev() {
+                e = e - x;
+            }
+            let e = e.compress();
+            assert_eq!(e.components(), &[tiny]);
+            assert_eq!(e.approx(), tiny);
+        }
+    }
+
+    #[test]
+    fn product() {
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b, c) = (rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>());
+            let ab = Expansion::from(a) * b;
+            let abc = (ab.clone() * c).compress();
+            assert_eq!(abc.sign(), Ordering::Greater);
+            let (up, down) = (abc.upper::<Emuf64>(), abc.lower::<Emuf64>());
+            assert!(down <= up && up <= down.succ().succ());
+            assert_ne!((Expansion::from(up) - abc.clone()).sign(), Ordering::Less);
+            assert_ne!((abc - down).sign(), Ordering::Less);
+            let d = (Expansion::from(a) * Expansion::from(b) - ab).compress();
+            assert_eq!(d.sign(), Ordering::Equal);
+        }
+    }
+
+    #[test]
+    fn compress_approx() {
+        let e = Expansion::from(1e100) + 1. + 1e-100;
+        assert_eq!(e.components().len(), 3);
+        let e = e.compress();
+        assert_eq!(e.approx(), 1e100);
+        assert!(e.lower::<Emuf64>() == 1e100 && e.upper::<Emuf64>() == 1e100.succ());
+        let e = e - 1e100;
+        assert_eq!(e.approx(), 1.);
+        assert!(e.lower::<Emuf64>() == 1. && e.upper::<Emuf64>() == 1.0f64.succ());
+    }
+}
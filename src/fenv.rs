@@ -0,0 +1,467 @@
+use core::cmp::Ordering;
+use core::hint::black_box;
+use core::ops::{Neg, Add, Sub, Mul, Div, Rem};
+
+use libc::c_int;
+use num_traits::{Num, Zero, One};
+use float_traits::Sqrt;
+
+use impl_session_scalar;
+use roundops::rmode::EditRoundingMode;
+use flags::{ExceptionFlags, ExceptionStatus};
+
+extern "C" {
+    fn fegetround() -> c_int;
+    fn fesetround(round: c_int) -> c_int;
+    fn fetestexcept(excepts: c_int) -> c_int;
+    fn feclearexcept(excepts: c_int) -> c_int;
+}
+
+// Values of the FE_* macros of <fenv.h>, which differ between architectures.
+#[cfg(target_env = "msvc")]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0x000;
+    pub const FE_DOWNWARD: c_int = 0x100;
+    pub const FE_UPWARD: c_int = 0x200;
+    pub const FE_TOWARDZERO: c_int = 0x300;
+    pub const FE_INEXACT: c_int = 0x01;
+    pub const FE_UNDERFLOW: c_int = 0x02;
+    pub const FE_OVERFLOW: c_int = 0x04;
+    pub const FE_DIVBYZERO: c_int = 0x08;
+    pub const FE_INVALID: c_int = 0x10;
+}
+
+#[cfg(all(not(target_env = "msvc"), any(target_arch = "x86", target_arch = "x86_64")))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0x000;
+    pub const FE_DOWNWARD: c_int = 0x400;
+    pub const FE_UPWARD: c_int = 0x800;
+    pub const FE_TOWARDZERO: c_int = 0xc00;
+    pub const FE_INVALID: c_int = 0x01;
+    pub const FE_DIVBYZERO: c_int = 0x04;
+    pub const FE_OVERFLOW: c_int = 0x08;
+    pub const FE_UNDERFLOW: c_int = 0x10;
+    pub const FE_INEXACT: c_int = 0x20;
+}
+
+#[cfg(all(not(target_env = "msvc"), any(target_arch = "arm", target_arch = "aarch64")))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0x000000;
+    pub const FE_UPWARD: c_int = 0x400000;
+    pub const FE_DOWNWARD: c_int = 0x800000;
+    pub const FE_TOWARDZERO: c_int = 0xc00000;
+    pub const FE_INVALID: c_int = 0x01;
+    pub const FE_DIVBYZERO: c_int = 0x02;
+    pub const FE_OVERFLOW: c_int = 0x04;
+    pub const FE_UNDERFLOW: c_int = 0x08;
+    pub const FE_INEXACT: c_int = 0x10;
+}
+
+#[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0;
+    pub const FE_TOWARDZERO: c_int = 1;
+    pub const FE_UPWARD: c_int = 2;
+    pub const FE_DOWNWARD: c_int = 3;
+    pub const FE_INEXACT: c_int = 0x02000000;
+    pub const FE_DIVBYZERO: c_int = 0x04000000;
+    pub const FE_UNDERFLOW: c_int = 0x08000000;
+    pub const FE_OVERFLOW: c_int = 0x10000000;
+    pub const FE_INVALID: c_int = 0x20000000;
+}
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0;
+    pub const FE_TOWARDZERO: c_int = 1;
+    pub const FE_UPWARD: c_int = 2;
+    pub const FE_DOWNWARD: c_int = 3;
+    pub const FE_INEXACT: c_int = 0x04;
+    pub const FE_UNDERFLOW: c_int = 0x08;
+    pub const FE_OVERFLOW: c_int = 0x10;
+    pub const FE_DIVBYZERO: c_int = 0x20;
+    pub const FE_INVALID: c_int = 0x40;
+}
+
+#[cfg(target_arch = "s390x")]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0;
+    pub const FE_TOWARDZERO: c_int = 1;
+    pub const FE_UPWARD: c_int = 2;
+    pub const FE_DOWNWARD: c_int = 3;
+    pub const FE_INEXACT: c_int = 0x08;
+    pub const FE_UNDERFLOW: c_int = 0x10;
+    pub const FE_OVERFLOW: c_int = 0x20;
+    pub const FE_DIVBYZERO: c_int = 0x40;
+    pub const FE_INVALID: c_int = 0x80;
+}
+
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0;
+    pub const FE_TOWARDZERO: c_int = 1;
+    pub const FE_DOWNWARD: c_int = 2;
+    pub const FE_UPWARD: c_int = 3;
+    pub const FE_INEXACT: c_int = 0x01;
+    pub const FE_UNDERFLOW: c_int = 0x02;
+    pub const FE_OVERFLOW: c_int = 0x04;
+    pub const FE_DIVBYZERO: c_int = 0x08;
+    pub const FE_INVALID: c_int = 0x10;
+}
+
+#[cfg(target_arch = "loongarch64")]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0x000;
+    pub const FE_TOWARDZERO: c_int = 0x100;
+    pub const FE_UPWARD: c_int = 0x200;
+    pub const FE_DOWNWARD: c_int = 0x300;
+    pub const FE_INEXACT: c_int = 0x010000;
+    pub const FE_UNDERFLOW: c_int = 0x020000;
+    pub const FE_OVERFLOW: c_int = 0x040000;
+    pub const FE_DIVBYZERO: c_int = 0x080000;
+    pub const FE_INVALID: c_int = 0x100000;
+}
+
+#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+mod consts {
+    use libc::c_int;
+    pub const FE_TONEAREST: c_int = 0;
+    pub const FE_TOWARDZERO: c_int = 0x40000000;
+    pub const FE_UPWARD: c_int = 0x80000000u32 as c_int;
+    pub const FE_DOWNWARD: c_int = 0xc0000000u32 as c_int;
+    pub const FE_INEXACT: c_int = 0x020;
+    pub const FE_DIVBYZERO: c_int = 0x040;
+    pub const FE_UNDERFLOW: c_int = 0x080;
+    pub const FE_OVERFLOW: c_int = 0x100;
+    pub const FE_INVALID: c_int = 0x200;
+}
+
+#[cfg(not(any(target_env = "msvc",
+              target_arch = "x86", target_arch = "x86_64",
+              target_arch = "arm", target_arch = "aarch64",
+              target_arch = "powerpc", target_arch = "powerpc64",
+              target_arch = "mips", target_arch = "mips64",
+              target_arch = "s390x",
+              target_arch = "riscv32", target_arch = "riscv64",
+              target_arch = "loongarch64",
+              target_arch = "sparc", target_arch = "sparc64")))]
+mod consts {
+    compile_error!("the `fenv` feature does not know the FE_* values of <fenv.h> for this target");
+}
+
+pub use self::consts::*;
+
+pub const FE_ALL_EXCEPT: c_int = FE_INVALID | FE_DIVBYZERO | FE_OVERFLOW | FE_UNDERFLOW |
+                                 FE_INEXACT;
+
+#[inline]
+pub fn get_round() -> c_int {
+    unsafe { fegetround() }
+}
+
+/// Sets the rounding direction of the calling thread to one of the `FE_*` values.
+///
+/// # Panics
+///
+/// If `fesetround` fails, since every directed result computed afterwards would be wrong.
+///
+/// # Safety
+///
+/// The compiler assumes rounding to nearest, so code run in another mode may be
+/// optimized as if it were not.
+#[inline]
+pub unsafe fn set_round(round: c_int) {
+    let r = fesetround(round);
+    assert_eq!(r, 0, "fesetround({}) failed", round);
+}
+
+/// Exception flags of the calling thread.
+pub fn test_except() -> ExceptionFlags {
+    let raised = unsafe { fetestexcept(FE_ALL_EXCEPT) };
+    let map = [(FE_INVALID, ExceptionFlags::INVALID),
+               (FE_DIVBYZERO, ExceptionFlags::DIVIDE_BY_ZERO),
+               (FE_OVERFLOW, ExceptionFlags::OVERFLOW),
+               (FE_UNDERFLOW, ExceptionFlags::UNDERFLOW),
+               (FE_INEXACT, ExceptionFlags::INEXACT)];
+    map.iter()
+        .filter(|&&(fe, _)| raised & fe != 0)
+        .fold(ExceptionFlags::empty(), |acc, &(_, f)| acc | f)
+}
+
+#[inline]
+pub fn clear_except() {
+    unsafe { feclearexcept(FE_ALL_EXCEPT) };
+}
+
+/// Float whose rounding direction is edited through the C library's `fesetround`.
+///
+/// The operations are the native ones of `T`, so it works on any target with a C99
+/// `<fenv.h>`. Where `rmode` has no hand-written backend, `T` itself uses this one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fenv<T>(pub T);
+
+macro_rules! impl_fenv_binop {
+    ($fxx:ident, $op:ident, $method:ident) => (
+        impl $op for Fenv<$fxx> {
+            type Output = Self;
+            #[inline]
+            fn $method(self, rhs: Self) -> Self {
+                Fenv(black_box($op::$method(black_box(self.0), black_box(rhs.0))))
+            }
+        }
+    )
+}
+
+macro_rules! impl_fenv {
+    ($fxx:ident) => (
+        impl_fenv_binop!($fxx, Add, add);
+        impl_fenv_binop!($fxx, Sub, sub);
+        impl_fenv_binop!($fxx, Mul, mul);
+        impl_fenv_binop!($fxx, Div, div);
+        impl_fenv_binop!($fxx, Rem, rem);
+
+        impl Neg for Fenv<$fxx> {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Fenv(-self.0)
+            }
+        }
+
+        impl Sqrt for Fenv<$fxx> {
+            type Output = Self;
+            #[inline]
+            fn sqrt(self) -> Self {
+                Fenv(black_box(black_box(self.0).sqrt()))
+            }
+        }
+
+        impl PartialEq for Fenv<$fxx> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl PartialOrd for Fenv<$fxx> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        impl From<$fxx> for Fenv<$fxx> {
+            fn from(x: $fxx) -> Self {
+                Fenv(x)
+            }
+        }
+
+        impl Zero for Fenv<$fxx> {
+            fn zero() -> Self {
+                Fenv(0.)
+            }
+            fn is_zero(&self) -> bool {
+                self.0 == 0.
+            }
+        }
+
+        impl One for Fenv<$fxx> {
+            fn one() -> Self {
+                Fenv(1.)
+            }
+        }
+
+        impl Num for Fenv<$fxx> {
+            type FromStrRadixErr = <$fxx as Num>::FromStrRadixErr;
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                <$fxx as Num>::from_str_radix(s, radix).map(Fenv)
+            }
+        }
+
+        impl EditRoundingMode for Fenv<$fxx> {
+            // one of the FE_* values
+            type RoundingState = c_int;
+
+            #[inline]
+            fn current_rounding_state() -> c_int {
+                get_round()
+            }
+            #[inline]
+            unsafe fn set_rounding_state(state: c_int) {
+                set_round(state);
+            }
+            #[inline]
+            unsafe fn upward() {
+                set_round(FE_UPWARD);
+            }
+            #[inline]
+            unsafe fn downward() {
+                set_round(FE_DOWNWARD);
+            }
+            #[inline]
+            unsafe fn to_nearest() {
+                set_round(FE_TONEAREST);
+            }
+            #[inline]
+            unsafe fn toward_zero() {
+                set_round(FE_TOWARDZERO);
+            }
+        }
+
+        impl ExceptionStatus for Fenv<$fxx> {
+            #[inline]
+            fn exception_flags() -> ExceptionFlags {
+                test_except()
+            }
+            #[inline]
+            fn clear_exception_flags() {
+                clear_except()
+            }
+        }
+
+        #[cfg(not(all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+        impl ExceptionStatus for $fxx {
+            #[inline]
+            fn exception_flags() -> ExceptionFlags {
+                test_except()
+            }
+            #[inline]
+            fn clear_exception_flags() {
+                clear_except()
+            }
+        }
+
+        #[cfg(not(all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+        impl EditRoundingMode for $fxx {
+            type RoundingState = c_int;
+
+            #[inline]
+            fn current_rounding_state() -> c_int {
+                get_round()
+            }
+            #[inline]
+            unsafe fn set_rounding_state(state: c_int) {
+                set_round(state);
+            }
+            #[inline]
+            unsafe fn upward() {
+                set_round(FE_UPWARD);
+            }
+            #[inline]
+            unsafe fn downward() {
+                set_round(FE_DOWNWARD);
+            }
+            #[inline]
+            unsafe fn to_nearest() {
+                set_round(FE_TONEAREST);
+            }
+            #[inline]
+            unsafe fn toward_zero() {
+                set_round(FE_TOWARDZERO);
+            }
+        }
+    )
+}
+
+impl_fenv!(f64);
+impl_fenv!(f32);
+
+impl_session_scalar!(<T> Fenv<T>);
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use methods::{Hardware, EmulationRegular};
+
+    use super::*;
+
+    #[test]
+    fn directed() {
+        type Hw = Hardware<Fenv<f64>>;
+        type Emu = EmulationRegular<f64>;
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>() - 0.5);
+            let (x, y) = (Fenv(a), Fenv(b));
+            assert_eq!(Hw::add_up(x, y).0, Emu::add_up(a, b));
+            assert_eq!(Hw::add_down(x, y).0, Emu::add_down(a, b));
+            assert_eq!(Hw::sub_up(x, y).0, Emu::sub_up(a, b));
+            assert_eq!(Hw::sub_down(x, y).0, Emu::sub_down(a, b));
+            assert_eq!(Hw::mul_up(x, y).0, Emu::mul_up(a, b));
+            assert_eq!(Hw::mul_down(x, y).0, Emu::mul_down(a, b));
+            assert_eq!(Hw::div_up(x, y).0, Emu::div_up(a, b));
+            assert_eq!(Hw::div_down(x, y).0, Emu::div_down(a, b));
+            assert_eq!(Hw::sqrt_up(x).0, Emu::sqrt_up(a));
+            assert_eq!(Hw::sqrt_down(x).0, Emu::sqrt_down(a));
+        }
+        assert_eq!(get_round(), FE_TONEAREST);
+    }
+
+    #[test]
+    fn raw_directions() {
+        let (one, tiny) = (1f64, f64::MIN_POSITIVE);
+        let modes = [(FE_UPWARD, 1. + f64::EPSILON, -1.),
+                     (FE_DOWNWARD, 1., -1. - f64::EPSILON),
+                     (FE_TOWARDZERO, 1., -1.),
+                     (FE_TONEAREST, 1., -1.)];
+        for &(fe, pos, neg) in &modes {
+            let got = unsafe {
+                set_round(fe);
+                let r = (black_box(black_box(one) + black_box(tiny)),
+                         black_box(-black_box(one) - black_box(tiny)));
+                set_round(FE_TONEAREST);
+                r
+            };
+            assert_eq!(got, (pos, neg));
+        }
+    }
+
+    #[test]
+    fn invalid_direction() {
+        use std::panic::catch_unwind;
+        assert!(catch_unwind(|| unsafe { set_round(-1) }).is_err());
+        assert_eq!(get_round(), FE_TONEAREST);
+    }
+
+    #[test]
+    fn flags() {
+        use flags::{RoundFlags, Tracked};
+        type Hw = Hardware<Fenv<f64>>;
+        type Emu = Tracked<EmulationRegular<f64>>;
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (rng.gen::<f64>() - 0.5, rng.gen::<f64>() * 2f64.powi(rng.gen_range(-900, 900)));
+            let (x, y) = (Fenv(a), Fenv(b));
+            Hw::clear_flags();
+            Emu::clear_flags();
+            assert_eq!(Hw::sub_down(x, y).0, Emu::sub_down(a, b));
+            assert_eq!(Hw::take_flags(), Emu::take_flags());
+            assert_eq!(Hw::div_down(y, x).0, Emu::div_down(b, a));
+            assert_eq!(Hw::take_flags(), Emu::take_flags());
+        }
+    }
+
+    #[cfg(all(feature = "hwrm", target_arch = "x86_64"))]
+    #[test]
+    fn agrees_with_mxcsr() {
+        use roundops::rmode::EditRoundingMode;
+        use x87::X87;
+        // fesetround sets both MXCSR and the x87 control word, while glibc's fegetround
+        // reads the latter only.
+        let modes = [(FE_UPWARD, 0x4000), (FE_DOWNWARD, 0x2000),
+                     (FE_TOWARDZERO, 0x6000), (FE_TONEAREST, 0)];
+        for &(fe, mxcsr) in &modes {
+            unsafe { set_round(fe) };
+            assert_eq!(f64::current_rounding_state(), mxcsr);
+            assert_eq!(X87::<f64>::current_rounding_state() & 0x0C00, fe as u16);
+            assert_eq!(get_round(), fe);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::ops::{Neg, BitOr, BitOrAssign, BitAnd};
+
+use num_traits::Num;
+use float_traits::IEEE754Float;
+
+use roundops::*;
+use roundops::rmode::EditRoundingMode;
+use methods::{Hardware, HardwareSession};
+
+/// Set of IEEE 754 exception flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExceptionFlags(u8);
+
+impl ExceptionFlags {
+    pub const INVALID: ExceptionFlags = ExceptionFlags(0x01);
+    pub const DIVIDE_BY_ZERO: ExceptionFlags = ExceptionFlags(0x02);
+    pub const OVERFLOW: ExceptionFlags = ExceptionFlags(0x04);
+    pub const UNDERFLOW: ExceptionFlags = ExceptionFlags(0x08);
+    pub const INEXACT: ExceptionFlags = ExceptionFlags(0x10);
+
+    pub const fn empty() -> Self {
+        ExceptionFlags(0)
+    }
+
+    pub fn all() -> Self {
+        ExceptionFlags(0x1F)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, other: ExceptionFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: ExceptionFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    // Flags in the layout of MXCSR and of the x87 status word.
+    #[cfg(all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64")))]
+    pub(crate) fn from_x86(status: u32) -> Self {
+        let mut f = ExceptionFlags::empty();
+        let map = [(0x01, ExceptionFlags::INVALID),
+                   (0x04, ExceptionFlags::DIVIDE_BY_ZERO),
+                   (0x08, ExceptionFlags::OVERFLOW),
+                   (0x10, ExceptionFlags::UNDERFLOW),
+                   (0x20, ExceptionFlags::INEXACT)];
+        for &(bit, flag) in &map {
+            if status & bit != 0 {
+                f |= flag;
+            }
+        }
+        f
+    }
+}
+
+impl BitOr for ExceptionFlags {
+    type Output = ExceptionFlags;
+    fn bitor(self, rhs: ExceptionFlags) -> ExceptionFlags {
+        ExceptionFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ExceptionFlags {
+    fn bitor_assign(&mut self, rhs: ExceptionFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for ExceptionFlags {
+    type Output = ExceptionFlags;
+    fn bitand(self, rhs: ExceptionFlags) -> ExceptionFlags {
+        ExceptionFlags(self.0 & rhs.0)
+    }
+}
+
+/// Sticky exception flags of the floating-point environment of a number type.
+pub trait ExceptionStatus {
+    fn exception_flags() -> ExceptionFlags;
+    fn clear_exception_flags();
+}
+
+/// Sticky exception flags raised on the current thread by the operations of a method.
+pub trait RoundFlags: RoundingMethod {
+    fn flags() -> ExceptionFlags;
+    fn clear_flags();
+
+    #[inline]
+    fn take_flags() -> ExceptionFlags {
+        let f = Self::flags();
+        Self::clear_flags();
+        f
+    }
+}
+
+impl<T: Num + EditRoundingMode + ExceptionStatus> RoundFlags for Hardware<T> {
+    #[inline]
+    fn flags() -> ExceptionFlags {
+        T::exception_flags()
+    }
+    #[inline]
+    fn clear_flags() {
+        T::clear_exception_flags()
+    }
+}
+
+impl<T, Dir> RoundFlags for HardwareSession<T, Dir>
+    where T: Num + Neg<Output = T> + Clone + EditRoundingMode + ExceptionStatus,
+          Dir: direction::Direction
+{
+    #[inline]
+    fn flags() -> ExceptionFlags {
+        T::exception_flags()
+    }
+    #[inline]
+    fn clear_flags() {
+        T::clear_exception_flags()
+    }
+}
+
+/// Emulation method `M` that also records the exception flags of its operations.
+///
+/// The flags are derived from the error terms `M` computes anyway, and are kept per
+/// thread for all tracked methods together. The plain method skips this bookkeeping.
+#[derive(Clone)]
+pub struct Tracked<M>(PhantomData<fn(M)>);
+
+impl<M: RoundingMethod> RoundingMethod for Tracked<M> {
+    type HostMethod = M::HostMethod;
+    type Num = M::Num;
+}
+
+impl<M: RoundingMethod> RoundFlags for Tracked<M> {
+    #[inline]
+    fn flags() -> ExceptionFlags {
+        EMULATION_FLAGS.with(|f| f.get())
+    }
+    #[inline]
+    fn clear_flags() {
+        EMULATION_FLAGS.with(|f| f.set(ExceptionFlags::empty()))
+    }
+}
+
+thread_local! {
+    static EMULATION_FLAGS: Cell<ExceptionFlags> = const { Cell::new(ExceptionFlags::empty()) };
+}
+
+// Where emulation methods put the flags they raise.
+pub(crate) trait FlagSink {
+    const ENABLED: bool;
+    fn raise(flags: ExceptionFlags);
+}
+
+pub(crate) enum Ignore {}
+
+impl FlagSink for Ignore {
+    const ENABLED: bool = false;
+    #[inline(always)]
+    fn raise(_: ExceptionFlags) {}
+}
+
+pub(crate) enum Sticky {}
+
+impl FlagSink for Sticky {
+    const ENABLED: bool = true;
+    #[inline]
+    fn raise(flags: ExceptionFlags) {
+        if !flags.is_empty() {
+            EMULATION_FLAGS.with(|f| f.set(f.get() | flags));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Specials {
+    pub(crate) nan: bool,
+    pub(crate) inf: bool,
+}
+
+#[inline]
+pub(crate) fn specials<T: IEEE754Float>(a: &T, b: &T) -> Specials {
+    Specials {
+        nan: a.partial_cmp(a).is_none() || b.partial_cmp(b).is_none(),
+        inf: a.is_infinite() || b.is_infinite(),
+    }
+}
+
+/// Raises the flags of an operation on operands described by `s` whose result rounded to
+/// nearest is `x`. Overflow is reported whenever `x` overflows, also when the directed
+/// result is `max_value`.
+#[inline]
+pub(crate) fn record<S: FlagSink, T: IEEE754Float + Clone>(x: &T, exact: bool, s: Specials) {
+    if !S::ENABLED {
+        return;
+    }
+    if x.partial_cmp(x).is_none() {
+        if !s.nan {
+            S::raise(ExceptionFlags::INVALID);
+        }
+    } else if x.is_infinite() {
+        if !s.inf {
+            S::raise(ExceptionFlags::OVERFLOW | ExceptionFlags::INEXACT);
+        }
+    } else if !exact {
+        if x.clone().abs() < T::min_positive() {
+            S::raise(ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT);
+        } else {
+            S::raise(ExceptionFlags::INEXACT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+    use float_traits::*;
+
+    use roundops::*;
+    use methods::{Hardware, EmulationRegular};
+    use binary128::F128;
+
+    use super::*;
+
+    fn both<H, E>(hw: H, emu: E) -> (ExceptionFlags, ExceptionFlags)
+        where H: FnOnce() -> F128,
+              E: FnOnce() -> F128
+    {
+        Hardware::<F128>::clear_flags();
+        let x = hw();
+        let hw_flags = Hardware::<F128>::take_flags();
+        Tracked::<EmulationRegular<F128>>::clear_flags();
+        let y = emu();
+        assert_eq!(x.to_bits(), y.to_bits());
+        (hw_flags, Tracked::<EmulationRegular<F128>>::take_flags())
+    }
+
+    #[test]
+    fn emulation_matches_software_hardware() {
+        type Hw = Hardware<F128>;
+        type Emu = Tracked<EmulationRegular<F128>>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b) = (F128::from(rng.gen::<f64>() - 0.5), F128::from(rng.gen::<f64>()));
+            let b = if rng.gen() { b } else { Emu::div_down(b, a) };
+            let (h, e) = both(|| Hw::add_up(a, b), || Emu::add_up(a, b));
+            assert_eq!(h, e);
+            let (h, e) = both(|| Hw::mul_down(a, b), || Emu::mul_down(a, b));
+            assert_eq!(h, e);
+            let (h, e) = both(|| Hw::div_up(a, b), || Emu::div_up(a, b));
+            assert_eq!(h, e);
+            let (h, e) = both(|| Hw::sqrt_down(b.abs()), || Emu::sqrt_down(b.abs()));
+            assert_eq!(h, e);
+        }
+    }
+
+    #[test]
+    fn exceptional() {
+        type Hw = Hardware<F128>;
+        type Emu = Tracked<EmulationRegular<F128>>;
+        let (max, one, zero) = (F128::MAX, F128::from(1.), F128::from(0.));
+        let tiny = F128::min_positive();
+
+        let (h, e) = both(|| Hw::add_down(max, max), || Emu::add_down(max, max));
+        assert!(h == e && e == ExceptionFlags::OVERFLOW | ExceptionFlags::INEXACT);
+        let third = one / F128::from(3.);
+        let (h, e) = both(|| Hw::mul_up(tiny, third), || Emu::mul_up(tiny, third));
+        assert!(h == e && e == ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT);
+        let (h, e) = both(|| Hw::mul_up(one, F128::from(0.3)), || Emu::mul_up(one, F128::from(0.3)));
+        assert!(h == e && e.is_empty());
+        let (h, _) = both(|| Hw::sub_up(F128::infinity(), F128::infinity()),
+                          || Emu::sub_up(F128::infinity(), F128::infinity()));
+        assert_eq!(h, ExceptionFlags::INVALID);
+        let (h, e) = both(|| Hw::div_up(one, zero), || Emu::div_up(one, zero));
+        assert!(h == e && e == ExceptionFlags::DIVIDE_BY_ZERO);
+        let (h, e) = both(|| Hw::sqrt_up(-one), || Emu::sqrt_up(-one));
+        assert!(h == e && e == ExceptionFlags::INVALID);
+    }
+
+    #[test]
+    fn untracked() {
+        type Emu = EmulationRegular<f64>;
+        Tracked::<Emu>::clear_flags();
+        assert_eq!(Emu::div_up(1., 3.), Tracked::<Emu>::div_up(1., 3.));
+        assert_eq!(Tracked::<Emu>::take_flags(), ExceptionFlags::INEXACT);
+        assert!(Emu::div_up(1., 0.) == f64::INFINITY && Emu::mul_down(f64::MAX, 2.) == f64::MAX);
+        assert!(Tracked::<Emu>::take_flags().is_empty());
+    }
+
+    #[cfg(all(feature = "hwrm", target_arch = "x86_64"))]
+    #[test]
+    fn mxcsr() {
+        type Hw = Hardware<f64>;
+        type Emu = Tracked<EmulationRegular<f64>>;
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (rng.gen::<f64>() - 0.5, rng.gen::<f64>() * 2f64.powi(rng.gen_range(-900, 900)));
+            Hw::clear_flags();
+            Emu::clear_flags();
+            assert_eq!(Hw::add_up(a, b), Emu::add_up(a, b));
+            assert_eq!(Hw::take_flags(), Emu::take_flags());
+            assert_eq!(Hw::mul_down(a, b), Emu::mul_down(a, b));
+            assert_eq!(Hw::take_flags(), Emu::take_flags());
+            assert_eq!(Hw::div_up(b, a), Emu::div_up(b, a));
+            assert_eq!(Hw::take_flags(), Emu::take_flags());
+        }
+    }
+}
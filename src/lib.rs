@@ -1,13 +1,23 @@
-#![cfg_attr(feature = "hwrm", feature(cfg_target_feature))]
-
 extern crate core;
 extern crate float_traits;
 extern crate num_traits;
+#[cfg(feature = "fenv")]
+extern crate libc;
 #[cfg(test)]
 extern crate rand;
 
 mod roundops;
 pub mod methods;
 pub mod utils;
+pub mod norm;
+pub mod predicates;
+pub mod expansion;
+pub mod double;
+pub mod binary128;
+pub mod flags;
+#[cfg(all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64")))]
+pub mod x87;
+#[cfg(feature = "fenv")]
+pub mod fenv;
 
 pub use roundops::*;
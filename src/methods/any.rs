@@ -0,0 +1,461 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Add, Sub, Mul, Div};
+use core::str::FromStr;
+use std::error::Error;
+use std::sync::OnceLock;
+
+use float_traits::*;
+
+use roundops::*;
+use utils::FloatSuccPred;
+use binary128::F128;
+
+use super::{Hardware, EmulationRegular, SuccPred, RoughWrapping};
+
+/// Object-safe counterpart of `RoundOps` and `RoundSqrt`, for choosing a method at run time.
+pub trait DynRoundOps<T> {
+    fn add_up(&self, a: T, b: T) -> T;
+    fn add_down(&self, a: T, b: T) -> T;
+    fn sub_up(&self, a: T, b: T) -> T;
+    fn sub_down(&self, a: T, b: T) -> T;
+    fn mul_up(&self, a: T, b: T) -> T;
+    fn mul_down(&self, a: T, b: T) -> T;
+    fn div_up(&self, a: T, b: T) -> T;
+    fn div_down(&self, a: T, b: T) -> T;
+    fn sqrt_up(&self, a: T) -> T;
+    fn sqrt_down(&self, a: T) -> T;
+    /// Type name of the method.
+    fn name(&self) -> &'static str;
+}
+
+/// The method `M` as a value implementing `DynRoundOps`.
+pub struct Method<M>(PhantomData<fn() -> M>);
+
+impl<M> Method<M> {
+    #[inline]
+    pub const fn new() -> Self {
+        Method(PhantomData)
+    }
+}
+
+impl<M> Default for Method<M> {
+    #[inline]
+    fn default() -> Self {
+        Method::new()
+    }
+}
+
+impl<M> Clone for Method<M> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for Method<M> {}
+
+impl<T, M> DynRoundOps<T> for Method<M>
+    where T: Add + Sub + Mul + Div,
+          M: RoundOps<T> + RoundSqrt
+{
+    #[inline]
+    fn add_up(&self, a: T, b: T) -> T {
+        M::add_up(a, b)
+    }
+    #[inline]
+    fn add_down(&self, a: T, b: T) -> T {
+        M::add_down(a, b)
+    }
+    #[inline]
+    fn sub_up(&self, a: T, b: T) -> T {
+        M::sub_up(a, b)
+    }
+    #[inline]
+    fn sub_down(&self, a: T, b: T) -> T {
+        M::sub_down(a, b)
+    }
+    #[inline]
+    fn mul_up(&self, a: T, b: T) -> T {
+        M::mul_up(a, b)
+    }
+    #[inline]
+    fn mul_down(&self, a: T, b: T) -> T {
+        M::mul_down(a, b)
+    }
+    #[inline]
+    fn div_up(&self, a: T, b: T) -> T {
+        M::div_up(a, b)
+    }
+    #[inline]
+    fn div_down(&self, a: T, b: T) -> T {
+        M::div_down(a, b)
+    }
+    #[inline]
+    fn sqrt_up(&self, a: T) -> T {
+        M::sqrt_up(a)
+    }
+    #[inline]
+    fn sqrt_down(&self, a: T) -> T {
+        M::sqrt_down(a)
+    }
+    fn name(&self) -> &'static str {
+        core::any::type_name::<M>()
+    }
+}
+
+/// The methods that `AnyMethod` can be built from, named as in configuration files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    Hardware,
+    EmulationRegular,
+    SuccPred,
+    RoughWrapping,
+}
+
+impl MethodKind {
+    pub const ALL: [MethodKind; 4] = [MethodKind::Hardware,
+                                      MethodKind::EmulationRegular,
+                                      MethodKind::SuccPred,
+                                      MethodKind::RoughWrapping];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MethodKind::Hardware => "hardware",
+            MethodKind::EmulationRegular => "emulation",
+            MethodKind::SuccPred => "succpred",
+            MethodKind::RoughWrapping => "roughwrap",
+        }
+    }
+}
+
+impl fmt::Display for MethodKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMethodKindError;
+
+impl fmt::Display for ParseMethodKindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("expected one of hardware, emulation, succpred or roughwrap")
+    }
+}
+
+impl Error for ParseMethodKindError {}
+
+impl FromStr for MethodKind {
+    type Err = ParseMethodKindError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MethodKind::ALL
+            .iter()
+            .cloned()
+            .find(|k| k.as_str() == s)
+            .ok_or(ParseMethodKindError)
+    }
+}
+
+/// A rounding method chosen at run time, dispatching through `DynRoundOps`.
+///
+/// ```
+/// use roundops::methods::{AnyMethod, DynRoundOps, MethodKind};
+/// use roundops::binary128::F128;
+///
+/// let kind = "succpred".parse::<MethodKind>().unwrap();
+/// let m = AnyMethod::<F128>::new(kind);
+/// assert!(m.add_down(F128::from(0.1), F128::from(0.2)) < m.add_up(F128::from(0.1), F128::from(0.2)));
+/// ```
+pub struct AnyMethod<T: 'static> {
+    ops: &'static (dyn DynRoundOps<T> + Sync),
+    kind: Option<MethodKind>,
+}
+
+impl<T: 'static> AnyMethod<T> {
+    /// Any method known at compile time.
+    #[inline]
+    pub fn of<M: RoundOps<T> + RoundSqrt + 'static>() -> Self
+        where T: Add + Sub + Mul + Div
+    {
+        AnyMethod {
+            ops: &Method::<M>(PhantomData),
+            kind: None,
+        }
+    }
+
+    /// The method, if it was built from a `MethodKind`.
+    #[inline]
+    pub fn kind(&self) -> Option<MethodKind> {
+        self.kind
+    }
+
+    #[inline]
+    fn of_kind<M: RoundOps<T> + RoundSqrt + 'static>(kind: MethodKind) -> Self
+        where T: Add + Sub + Mul + Div
+    {
+        AnyMethod { kind: Some(kind), ..Self::of::<M>() }
+    }
+}
+
+impl<T> AnyMethod<T>
+    where T: IEEE754Float + FloatSuccPred + Infinite + BoundedFloat + Abs<Output = T> + Underflow +
+             Sqrt<Output = T> + Clone + 'static
+{
+    /// A method that does not change the rounding mode, or `None` for `Hardware`.
+    pub fn software(kind: MethodKind) -> Option<Self> {
+        match kind {
+            MethodKind::Hardware => None,
+            MethodKind::EmulationRegular => Some(Self::of_kind::<EmulationRegular<T>>(kind)),
+            MethodKind::SuccPred => Some(Self::of_kind::<SuccPred<T>>(kind)),
+            MethodKind::RoughWrapping => Some(Self::of_kind::<RoughWrapping<T>>(kind)),
+        }
+    }
+
+    pub fn new(kind: MethodKind) -> Self
+        where T: rmode::EditRoundingMode
+    {
+        match kind {
+            MethodKind::Hardware => Self::of_kind::<Hardware<T>>(kind),
+            _ => Self::software(kind).unwrap(),
+        }
+    }
+}
+
+impl<T: 'static> Clone for AnyMethod<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: 'static> Copy for AnyMethod<T> {}
+
+impl<T: 'static> fmt::Debug for AnyMethod<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "AnyMethod({})", kind),
+            None => write!(f, "AnyMethod({})", self.ops.name()),
+        }
+    }
+}
+
+impl<T: 'static> DynRoundOps<T> for AnyMethod<T> {
+    #[inline]
+    fn add_up(&self, a: T, b: T) -> T {
+        self.ops.add_up(a, b)
+    }
+    #[inline]
+    fn add_down(&self, a: T, b: T) -> T {
+        self.ops.add_down(a, b)
+    }
+    #[inline]
+    fn sub_up(&self, a: T, b: T) -> T {
+        self.ops.sub_up(a, b)
+    }
+    #[inline]
+    fn sub_down(&self, a: T, b: T) -> T {
+        self.ops.sub_down(a, b)
+    }
+    #[inline]
+    fn mul_up(&self, a: T, b: T) -> T {
+        self.ops.mul_up(a, b)
+    }
+    #[inline]
+    fn mul_down(&self, a: T, b: T) -> T {
+        self.ops.mul_down(a, b)
+    }
+    #[inline]
+    fn div_up(&self, a: T, b: T) -> T {
+        self.ops.div_up(a, b)
+    }
+    #[inline]
+    fn div_down(&self, a: T, b: T) -> T {
+        self.ops.div_down(a, b)
+    }
+    #[inline]
+    fn sqrt_up(&self, a: T) -> T {
+        self.ops.sqrt_up(a)
+    }
+    #[inline]
+    fn sqrt_down(&self, a: T) -> T {
+        self.ops.sqrt_down(a)
+    }
+    fn name(&self) -> &'static str {
+        self.ops.name()
+    }
+}
+
+/// Number types with a process-wide `AnyMethod` for `Selected`.
+pub trait Selectable: Clone + 'static {
+    fn selected() -> &'static OnceLock<AnyMethod<Self>>;
+    /// The method used if none was selected before the first operation.
+    fn fallback() -> AnyMethod<Self>;
+}
+
+macro_rules! impl_selectable {
+    ($($t:ty),*) => ($(
+        impl Selectable for $t {
+            #[inline]
+            fn selected() -> &'static OnceLock<AnyMethod<$t>> {
+                static SELECTED: OnceLock<AnyMethod<$t>> = OnceLock::new();
+                &SELECTED
+            }
+            fn fallback() -> AnyMethod<$t> {
+                AnyMethod::of_kind::<EmulationRegular<$t>>(MethodKind::EmulationRegular)
+            }
+        }
+    )*)
+}
+
+impl_selectable!(f32, f64, F128);
+
+/// The `AnyMethod` selected for the whole process, as a method type.
+///
+/// It can be the method of `RoundedNum`, `RoundedSession` and generic code such as
+/// `norm` and `predicates`, while the method itself is read from the configuration at
+/// startup. It is hosted like `DefaultRounding` methods, whichever method is selected.
+///
+/// ```
+/// use roundops::RoundedNum;
+/// use roundops::direction::Upward;
+/// use roundops::methods::{AnyMethod, MethodKind, Selected};
+/// use roundops::binary128::F128;
+///
+/// let kind = "succpred".parse::<MethodKind>().unwrap();
+/// Selected::<F128>::select(AnyMethod::new(kind)).unwrap();
+/// let x = RoundedNum::<Upward, F128, Selected<F128>>::new(F128::from(1.)) + F128::from(0.);
+/// assert!(x.extract() > F128::from(1.));
+/// ```
+pub struct Selected<T>(PhantomData<fn(T)>);
+
+impl<T: Selectable> Selected<T> {
+    /// Selects the method for the rest of the process. Fails, returning `method`, if a
+    /// method was already selected or an operation already used the fallback.
+    pub fn select(method: AnyMethod<T>) -> Result<(), AnyMethod<T>> {
+        T::selected().set(method)
+    }
+
+    /// The method in use, fixing the fallback if none was selected yet.
+    #[inline]
+    pub fn method() -> AnyMethod<T> {
+        *T::selected().get_or_init(T::fallback)
+    }
+}
+
+impl<T> Clone for Selected<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Selected(PhantomData)
+    }
+}
+
+impl<T: Selectable> RoundingMethod for Selected<T> {
+    type HostMethod = rmode::DefaultRounding;
+    type Num = T;
+}
+
+macro_rules! impl_selected_ops {
+    ($($tr:ident, $up:ident, $down:ident);+) => ($(
+        impl<T: Selectable> $tr for Selected<T> {
+            #[inline]
+            fn $up(a: T, b: T) -> T {
+                Self::method().$up(a, b)
+            }
+            #[inline]
+            fn $down(a: T, b: T) -> T {
+                Self::method().$down(a, b)
+            }
+        }
+    )+)
+}
+
+impl_selected_ops!(RoundAdd, add_up, add_down; RoundSub, sub_up, sub_down;
+                   RoundMul, mul_up, mul_down; RoundDiv, div_up, div_down);
+
+impl<T: Selectable> RoundSqrt for Selected<T> {
+    #[inline]
+    fn sqrt_up(a: T) -> T {
+        Self::method().sqrt_up(a)
+    }
+    #[inline]
+    fn sqrt_down(a: T) -> T {
+        Self::method().sqrt_down(a)
+    }
+}
+
+impl<T: Selectable> RoundedSession for Selected<T> {
+    type Num = T;
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use methods::{Hardware, EmulationRegular, SuccPred, RoughWrapping};
+    use binary128::F128;
+
+    use super::{AnyMethod, DynRoundOps, MethodKind, Selected};
+
+    #[test]
+    fn dispatch() {
+        macro_rules! check {
+            ($($kind:ident => $m:ty),*) => ($({
+                let m = AnyMethod::<F128>::new(MethodKind::$kind.to_string().parse().unwrap());
+                assert_eq!(m.kind(), Some(MethodKind::$kind));
+                let mut rng = thread_rng();
+                for _ in 0..1000 {
+                    let (a, b) = (F128::from(rng.gen::<f64>()), F128::from(rng.gen::<f64>() - 0.5));
+                    let c = EmulationRegular::<F128>::div_up(a, b);
+                    let got = [m.add_up(a, c), m.add_down(a, c), m.sub_up(a, c), m.sub_down(a, c),
+                               m.mul_up(a, c), m.mul_down(a, c), m.div_up(a, c), m.div_down(a, c),
+                               m.sqrt_up(a), m.sqrt_down(a)];
+                    let expected = [<$m>::add_up(a, c), <$m>::add_down(a, c),
+                                    <$m>::sub_up(a, c), <$m>::sub_down(a, c),
+                                    <$m>::mul_up(a, c), <$m>::mul_down(a, c),
+                                    <$m>::div_up(a, c), <$m>::div_down(a, c),
+                                    <$m>::sqrt_up(a), <$m>::sqrt_down(a)];
+                    for (x, y) in got.iter().zip(expected.iter()) {
+                        assert_eq!(x.to_bits(), y.to_bits());
+                    }
+                }
+            })*)
+        }
+        check!(Hardware => Hardware<F128>,
+               EmulationRegular => EmulationRegular<F128>,
+               SuccPred => SuccPred<F128>,
+               RoughWrapping => RoughWrapping<F128>);
+        assert_eq!(AnyMethod::<F128>::software(MethodKind::Hardware).map(|m| m.kind()), None);
+        let m = AnyMethod::<f64>::of::<SuccPred<f64>>();
+        assert_eq!((m.kind(), m.add_up(1., 0.)), (None, 1. + f64::EPSILON));
+        assert!(format!("{:?}", m).contains("SuccPred"));
+        assert!("hw".parse::<MethodKind>().is_err());
+    }
+
+    #[test]
+    fn selected() {
+        use roundops::direction::{Upward, Downward};
+        use norm::norm2_up;
+        use predicates::orient2d;
+
+        type Up = RoundedNum<Upward, F128, Selected<F128>>;
+        type Emu = EmulationRegular<F128>;
+        assert!(Selected::<F128>::select(AnyMethod::new(MethodKind::SuccPred)).is_ok());
+        assert!(Selected::<F128>::select(AnyMethod::new(MethodKind::RoughWrapping)).is_err());
+        assert_eq!(Selected::<F128>::method().kind(), Some(MethodKind::SuccPred));
+        let (one, three) = (F128::from(1.), F128::from(3.));
+        let third = (Up::new(one) / three).extract();
+        assert_eq!(third.to_bits(), SuccPred::<F128>::div_up(one, three).to_bits());
+        assert!(third >= Emu::div_up(one, three));
+        let (lo, hi) = Selected::<F128>::compute::<Downward, _, _, _>(
+            [one, three], |[a, b]| (a / b, (-(a / b)).extract()));
+        assert!(lo <= Emu::div_down(one, three) && -hi == lo);
+        let v = [three, F128::from(4.)];
+        assert!(norm2_up::<Selected<F128>>(&v) >= F128::from(5.));
+        assert_eq!(orient2d::<Selected<F128>>(&[F128::from(0.), F128::from(0.)],
+                                              &[one, F128::from(0.)],
+                                              &[F128::from(0.), one]),
+                   ::core::cmp::Ordering::Greater);
+    }
+}
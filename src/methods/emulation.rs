@@ -9,13 +9,19 @@ use utils::safeeft::safetwoproduct_fma;
 #[cfg(any(feature = "use-fma", feature = "doc"))]
 use utils::fma::Fma;
 
-use utils::FloatSuccPred;
+use utils::{FloatSuccPred, RuntimeFma, safetwoproduct_auto};
+use flags::{record, specials, ExceptionFlags, FlagSink, Ignore, Sticky, Tracked};
 
 #[derive(Clone)]
 pub struct EmulationRegular<T: IEEE754Float + Clone>(PhantomData<fn(T)>);
 #[cfg(any(feature = "use-fma", feature = "doc"))]
 #[derive(Clone)]
 pub struct EmulationFma<T: IEEE754Float + Fma + Clone>(PhantomData<fn(T)>);
+/// `EmulationRegular` that uses the fused multiply-add when the CPU running it has one.
+///
+/// The CPU is checked once, on the first multiplication, division or square root.
+#[derive(Clone)]
+pub struct EmulationAuto<T: RuntimeFma>(PhantomData<fn(T)>);
 
 impl<T: IEEE754Float + Clone> RoundingMethod for EmulationRegular<T> {
     type HostMethod = rmode::DefaultRounding;
@@ -28,11 +34,17 @@ impl<T: IEEE754Float + Fma + Clone> RoundingMethod for EmulationFma<T> {
     type Num = T;
 }
 
+impl<T: RuntimeFma> RoundingMethod for EmulationAuto<T> {
+    type HostMethod = rmode::DefaultRounding;
+    type Num = T;
+}
+
 macro_rules! impl_rops {
     ($bound:ident $(+$bound1:ident)+, $method:ident, $twoproduct:ident) => (
-        impl<T: $($bound1+)+$bound> RoundAdd for $method<T> {
-            fn add_up(a: T, b: T) -> T {
+        impl<T: $($bound1+)+$bound> $method<T> {
+            fn add_up_with<S: FlagSink>(a: T, b: T) -> T {
                 let (x, y) = safetwosum(a.clone(), b.clone());
+                record::<S, _>(&x, y == T::zero(), specials(&a, &b));
                 if x == T::infinity() {
                     x
                 } else if x == T::neg_infinity() {
@@ -45,8 +57,9 @@ macro_rules! impl_rops {
                     if y > T::zero() { x.succ() } else { x }
                 }
             }
-            fn add_down(a: T, b: T) -> T {
+            fn add_down_with<S: FlagSink>(a: T, b: T) -> T {
                 let (x, y) = safetwosum(a.clone(), b.clone());
+                record::<S, _>(&x, y == T::zero(), specials(&a, &b));
                 if x == T::infinity() {
                     if a == T::neg_infinity() || b == T::neg_infinity() {
                         x
@@ -61,20 +74,24 @@ macro_rules! impl_rops {
             }
         }
 
-        impl<T: $($bound1+)+$bound> RoundSub for $method<T> {
+        impl<T: $($bound1+)+$bound> $method<T> {
             #[inline]
-            fn sub_up(a: T, b: T) -> T {
-                Self::add_up(a, -b)
+            fn sub_up_with<S: FlagSink>(a: T, b: T) -> T {
+                Self::add_up_with::<S>(a, -b)
             }
             #[inline]
-            fn sub_down(a: T, b: T) -> T {
-                Self::add_down(a, -b)
+            fn sub_down_with<S: FlagSink>(a: T, b: T) -> T {
+                Self::add_down_with::<S>(a, -b)
             }
         }
 
-        impl<T: $($bound1+)+$bound> RoundMul for $method<T> {
-            fn mul_up(a: T, b: T) -> T {
+        impl<T: $($bound1+)+$bound> $method<T> {
+            fn mul_up_with<S: FlagSink>(a: T, b: T) -> T {
+                let s = specials(&a, &b);
                 let (x, y) = $twoproduct(a.clone(), b.clone());
+                if x.is_infinite() {
+                    record::<S, _>(&x, false, s);
+                }
                 if x == T::infinity() {
                     x
                 } else if x == T::neg_infinity() {
@@ -85,6 +102,7 @@ macro_rules! impl_rops {
                     }
                 } else {
                     if x.clone().abs() > T::min_positive() / T::eps() * T::radix() {
+                        record::<S, _>(&x, y == T::zero(), s);
                         if y > T::zero() { x.succ() } else { x }
                     } else {
                         use num_traits::One;
@@ -104,6 +122,7 @@ macro_rules! impl_rops {
                                 T::radix_powi(T::bit_size() -
                                             (T::min_exponent() /
                                             (T::Exponent::one() + T::Exponent::one())));
+                        record::<S, _>(&x, t == s_h && s_l == T::zero(), s);
                         if t < s_h || (t == s_h && s_l > T::zero()) {
                             x.succ()
                         } else {
@@ -112,8 +131,12 @@ macro_rules! impl_rops {
                     }
                 }
             }
-            fn mul_down(a: T, b: T) -> T {
+            fn mul_down_with<S: FlagSink>(a: T, b: T) -> T {
+                let s = specials(&a, &b);
                 let (x, y) = $twoproduct(a.clone(), b.clone());
+                if x.is_infinite() {
+                    record::<S, _>(&x, false, s);
+                }
                 if x == T::infinity() {
                     if a == T::infinity() || b == T::infinity() {
                         // TODO: check
@@ -125,6 +148,7 @@ macro_rules! impl_rops {
                     x
                 } else {
                     if x.clone().abs() > T::min_positive() / T::eps() * T::radix() {
+                        record::<S, _>(&x, y == T::zero(), s);
                         if y < T::zero() { x.pred() } else { x }
                     } else {
                         use num_traits::One;
@@ -144,6 +168,7 @@ macro_rules! impl_rops {
                                 T::radix_powi(T::bit_size() -
                                             (T::min_exponent() /
                                             (T::Exponent::one() + T::Exponent::one())));
+                        record::<S, _>(&x, t == s_h && s_l == T::zero(), s);
                         if t > s_h || (t == s_h && s_l < T::zero()) {
                             x.pred()
                         } else {
@@ -154,22 +179,35 @@ macro_rules! impl_rops {
             }
         }
 
-        impl<T: $($bound1+)+$bound> RoundDiv for $method<T> {
-            fn div_up(a: T, b: T) -> T {
+        impl<T: $($bound1+)+$bound> $method<T> {
+            fn div_up_with<S: FlagSink>(a: T, b: T) -> T {
                 if a == T::zero() || b == T::zero() || a.clone().abs() == T::infinity() ||
                 b.clone().abs() == T::infinity() || a != a || b != b {
-                    a / b
+                    let s = specials(&a, &b);
+                    let pole = b == T::zero() && a != T::zero() && !s.nan && !s.inf;
+                    let r = a / b;
+                    if pole {
+                        S::raise(ExceptionFlags::DIVIDE_BY_ZERO);
+                    } else {
+                        record::<S, _>(&r, true, s);
+                    }
+                    r
                 } else {
+                    let s = specials(&a, &b);
                     let (mut ss, mut bb) = (a, b);
                     if bb < T::zero() {
                         ss = ss * -T::one();
                         bb = bb * -T::one();
                     }
+                    // The error of `d * bb` below is representable only if `ss` is not tiny.
+                    // Scale both up unless `bb` would overflow, in which case the quotient
+                    // is below the smallest subnormal.
                     if ss.clone().abs() < T::min_positive() / T::eps() * T::radix() {
-                        if bb.clone().abs() < T::min_positive() / T::eps() / T::eps() {
-                            ss = ss * (T::radix() * T::eps() * T::eps());
-                            bb = bb * (T::radix() * T::eps() * T::eps());
+                        if bb < T::max_value() * (T::eps() * T::eps() / T::radix()) {
+                            ss = ss * (T::radix() / T::eps() / T::eps());
+                            bb = bb * (T::radix() / T::eps() / T::eps());
                         } else {
+                            S::raise(ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT);
                             if ss < T::zero() {
                                 return T::zero();
                             } else {
@@ -179,9 +217,11 @@ macro_rules! impl_rops {
                     }
                     let d = ss.clone() / bb.clone();
                     if d.is_infinite() {
+                        record::<S, _>(&d, false, s);
                         if d > T::zero() { d } else { T::min_value() }
                     } else {
                         let (x, y) = $twoproduct(d.clone(), bb.clone());
+                        record::<S, _>(&d, x == ss && y == T::zero(), s);
                         if x < ss || (x == ss && y < T::zero()) {
                             d.succ()
                         } else {
@@ -190,22 +230,35 @@ macro_rules! impl_rops {
                     }
                 }
             }
-            fn div_down(a: T, b: T) -> T {
+            fn div_down_with<S: FlagSink>(a: T, b: T) -> T {
                 if a == T::zero() || b == T::zero() || a.clone().abs() == T::infinity() ||
                 b.clone().abs() == T::infinity() || a != a || b != b {
-                    a / b
+                    let s = specials(&a, &b);
+                    let pole = b == T::zero() && a != T::zero() && !s.nan && !s.inf;
+                    let r = a / b;
+                    if pole {
+                        S::raise(ExceptionFlags::DIVIDE_BY_ZERO);
+                    } else {
+                        record::<S, _>(&r, true, s);
+                    }
+                    r
                 } else {
+                    let s = specials(&a, &b);
                     let (mut ss, mut bb) = (a, b);
 
                     if bb < T::zero() {
                         ss = ss * -T::one();
                         bb = bb * -T::one();
                     }
+                    // The error of `d * bb` below is representable only if `ss` is not tiny.
+                    // Scale both up unless `bb` would overflow, in which case the quotient
+                    // is below the smallest subnormal.
                     if ss.clone().abs() < T::min_positive() / T::eps() * T::radix() {
-                        if bb.clone().abs() < T::min_positive() / T::eps() / T::eps() {
-                            ss = ss * (T::radix() * T::eps() * T::eps());
-                            bb = bb * (T::radix() * T::eps() * T::eps());
+                        if bb < T::max_value() * (T::eps() * T::eps() / T::radix()) {
+                            ss = ss * (T::radix() / T::eps() / T::eps());
+                            bb = bb * (T::radix() / T::eps() / T::eps());
                         } else {
+                            S::raise(ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT);
                             if ss < T::zero() {
                                 return -T::unit_underflow();
                             } else {
@@ -215,9 +268,11 @@ macro_rules! impl_rops {
                     }
                     let d = ss.clone() / bb.clone();
                     if d.is_infinite() {
+                        record::<S, _>(&d, false, s);
                         if d > T::zero() { T::max_value() } else { d }
                     } else {
                         let (x, y) = $twoproduct(d.clone(), bb.clone());
+                        record::<S, _>(&d, x == ss && y == T::zero(), s);
                         if x > ss || (x == ss && y > T::zero()) {
                             d.pred()
                         } else {
@@ -228,13 +283,15 @@ macro_rules! impl_rops {
             }
         }
 
-        impl<T: $($bound1+)+$bound> RoundSqrt for $method<T> {
-            fn sqrt_up(a: T) -> T {
+        impl<T: $($bound1+)+$bound> $method<T> {
+            fn sqrt_up_with<S: FlagSink>(a: T) -> T {
+                let s = specials(&a, &a);
                 let r = a.clone().sqrt();
                 if a < T::min_positive() / T::eps() * T::radix() {
                     let (ss, rr) = (a * (T::radix() / T::eps() * T::radix() / T::eps()),
                                     r.clone() * (T::radix() / T::eps()));
                     let (x, y) = $twoproduct(ss.clone(), rr.clone());
+                    record::<S, _>(&r, x == ss && y == T::zero(), s);
                     if x < ss || (x == ss && y < T::zero()) {
                         r.succ()
                     } else {
@@ -242,6 +299,7 @@ macro_rules! impl_rops {
                     }
                 } else {
                     let (x, y) = $twoproduct(r.clone(), r.clone());
+                    record::<S, _>(&r, x == a && y == T::zero(), s);
                     if x < a || (x == a && y < T::zero()) {
                         r.succ()
                     } else {
@@ -249,12 +307,14 @@ macro_rules! impl_rops {
                     }
                 }
             }
-            fn sqrt_down(a: T) -> T {
+            fn sqrt_down_with<S: FlagSink>(a: T) -> T {
+                let s = specials(&a, &a);
                 let r = a.clone().sqrt();
                 if a < T::min_positive() / T::eps() * T::radix() {
                     let (ss, rr) = (a * (T::radix() / T::eps() * T::radix() / T::eps()),
                                     r.clone() * T::radix() / T::eps());
                     let (x, y) = $twoproduct(ss.clone(), rr.clone());
+                    record::<S, _>(&r, x == ss && y == T::zero(), s);
                     if x > ss || (x == ss && y > T::zero()) {
                         r.pred()
                     } else {
@@ -262,6 +322,7 @@ macro_rules! impl_rops {
                     }
                 } else {
                     let (x, y) = $twoproduct(r.clone(), r.clone());
+                    record::<S, _>(&r, x == a && y == T::zero(), s);
                     if x > a || (x == a && y > T::zero()) {
                         r.pred()
                     } else {
@@ -270,6 +331,70 @@ macro_rules! impl_rops {
                 }
             }
         }
+
+        // The plain method ignores the flags, so their bookkeeping compiles away.
+        impl_rops!(@forward $bound $(+$bound1)+, $method, $method<T>, Ignore);
+        impl_rops!(@forward $bound $(+$bound1)+, $method, Tracked<$method<T>>, Sticky);
+    );
+    (@forward $bound:ident $(+$bound1:ident)+, $method:ident, $target:ty, $sink:ident) => (
+        impl<T: $($bound1+)+$bound> RoundAdd for $target {
+            #[inline]
+            fn add_up(a: T, b: T) -> T {
+                $method::<T>::add_up_with::<$sink>(a, b)
+            }
+            #[inline]
+            fn add_down(a: T, b: T) -> T {
+                $method::<T>::add_down_with::<$sink>(a, b)
+            }
+        }
+
+        impl<T: $($bound1+)+$bound> RoundSub for $target {
+            #[inline]
+            fn sub_up(a: T, b: T) -> T {
+                $method::<T>::sub_up_with::<$sink>(a, b)
+            }
+            #[inline]
+            fn sub_down(a: T, b: T) -> T {
+                $method::<T>::sub_down_with::<$sink>(a, b)
+            }
+        }
+
+        impl<T: $($bound1+)+$bound> RoundMul for $target {
+            #[inline]
+            fn mul_up(a: T, b: T) -> T {
+                $method::<T>::mul_up_with::<$sink>(a, b)
+            }
+            #[inline]
+            fn mul_down(a: T, b: T) -> T {
+                $method::<T>::mul_down_with::<$sink>(a, b)
+            }
+        }
+
+        impl<T: $($bound1+)+$bound> RoundDiv for $target {
+            #[inline]
+            fn div_up(a: T, b: T) -> T {
+                $method::<T>::div_up_with::<$sink>(a, b)
+            }
+            #[inline]
+            fn div_down(a: T, b: T) -> T {
+                $method::<T>::div_down_with::<$sink>(a, b)
+            }
+        }
+
+        impl<T: $($bound1+)+$bound> RoundSqrt for $target {
+            #[inline]
+            fn sqrt_up(a: T) -> T {
+                $method::<T>::sqrt_up_with::<$sink>(a)
+            }
+            #[inline]
+            fn sqrt_down(a: T) -> T {
+                $method::<T>::sqrt_down_with::<$sink>(a)
+            }
+        }
+
+        impl<T: $($bound1+)+$bound> RoundedSession for $target {
+            type Num = T;
+        }
     )
 }
 
@@ -278,14 +403,7 @@ impl_rops!(IEEE754Float + Clone,
            safetwoproduct_branch);
 #[cfg(any(feature = "use-fma", feature = "doc"))]
 impl_rops!(IEEE754Float + Fma + Clone, EmulationFma, safetwoproduct_fma);
-
-impl<T: IEEE754Float + Clone> RoundedSession for EmulationRegular<T> {
-    type Num = T;
-}
-#[cfg(any(feature = "use-fma", feature = "doc"))]
-impl<T: IEEE754Float + Fma + Clone> RoundedSession for EmulationFma<T> {
-    type Num = T;
-}
+impl_rops!(RuntimeFma + Clone, EmulationAuto, safetwoproduct_auto);
 
 #[cfg(test)]
 mod tests {
@@ -298,6 +416,32 @@ mod tests {
 
     type Emuf64 = EmulationRegular<f64>;
 
+    #[test]
+    fn auto() {
+        use utils::{fma_available, RuntimeFma};
+        use utils::safeeft::safetwoproduct_branch;
+        use super::EmulationAuto;
+
+        type Auto = EmulationAuto<f64>;
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, b) = (rng.gen::<f64>() - 0.5, rng.gen::<f64>() * 1e-300);
+            let c = rng.gen::<f64>();
+            if fma_available() {
+                assert_eq!(unsafe { f64::twoproduct_fma(a, c) }, safetwoproduct_branch(a, c));
+            }
+            assert_eq!([Auto::mul_up(a, b), Auto::mul_down(a, b),
+                        Auto::div_up(a, b), Auto::div_down(a, b),
+                        Auto::sqrt_up(b), Auto::sqrt_down(b)],
+                       [Emuf64::mul_up(a, b), Emuf64::mul_down(a, b),
+                        Emuf64::div_up(a, b), Emuf64::div_down(a, b),
+                        Emuf64::sqrt_up(b), Emuf64::sqrt_down(b)]);
+        }
+        let a = 1. + 2f32.powi(-12);
+        assert_eq!(EmulationAuto::<f32>::mul_up(a, a), 1. + 2f32.powi(-11) + f32::EPSILON);
+        assert_eq!(EmulationAuto::<f32>::mul_down(a, a), 1. + 2f32.powi(-11));
+    }
+
     #[test]
     fn addition() {
         let mut rng = thread_rng();
@@ -356,6 +500,10 @@ mod tests {
                 assert!(x != x && y != y);
             }
         }
+        let (a, b) = (2f64.powi(-970), 2f64.powi(-900));
+        assert_eq!((Emuf64::div_up(a, b), Emuf64::div_down(a, b)),
+                   (2f64.powi(-70), 2f64.powi(-70)));
+        assert_eq!((Emuf64::div_up(a, 1e300), Emuf64::div_down(-a, 1e300)), (5e-324, -5e-324));
     }
 
     #[test]
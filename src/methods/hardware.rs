@@ -1,3 +1,4 @@
+use core::hint::black_box;
 use core::marker::PhantomData;
 
 use num_traits::Num;
@@ -5,6 +6,10 @@ use float_traits::Sqrt;
 
 use roundops::*;
 
+/// Hardware rounding that switches the mode of the calling thread for every operation.
+///
+/// Each operation restores the mode it found, so it can be used on any thread, across
+/// `.await` and under a `RoundingGuard` of any direction.
 #[derive(Clone)]
 pub struct Hardware<T: Num + rmode::EditRoundingMode>(PhantomData<fn(T)>);
 
@@ -17,18 +22,41 @@ impl<T: Num + rmode::EditRoundingMode> RoundAdd for Hardware<T> {
     #[inline(never)]
     fn add_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::upward();
-            let r = lhs + rhs;
-            T::to_nearest();
+            // `black_box` keeps the compiler from moving the operation out of the switched mode.
+            let r = black_box(black_box(lhs) + black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
     #[inline(never)]
     fn add_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::downward();
-            let r = lhs + rhs;
+            let r = black_box(black_box(lhs) + black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn add_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
+            T::toward_zero();
+            let r = black_box(black_box(lhs) + black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn add_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
             T::to_nearest();
+            let r = black_box(black_box(lhs) + black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
@@ -38,18 +66,40 @@ impl<T: Num + rmode::EditRoundingMode> RoundSub for Hardware<T> {
     #[inline(never)]
     fn sub_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::upward();
-            let r = lhs - rhs;
-            T::to_nearest();
+            let r = black_box(black_box(lhs) - black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
     #[inline(never)]
     fn sub_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::downward();
-            let r = lhs - rhs;
+            let r = black_box(black_box(lhs) - black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn sub_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
+            T::toward_zero();
+            let r = black_box(black_box(lhs) - black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn sub_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
             T::to_nearest();
+            let r = black_box(black_box(lhs) - black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
@@ -59,18 +109,40 @@ impl<T: Num + rmode::EditRoundingMode> RoundMul for Hardware<T> {
     #[inline(never)]
     fn mul_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::upward();
-            let r = lhs * rhs;
-            T::to_nearest();
+            let r = black_box(black_box(lhs) * black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
     #[inline(never)]
     fn mul_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::downward();
-            let r = lhs * rhs;
+            let r = black_box(black_box(lhs) * black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn mul_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
+            T::toward_zero();
+            let r = black_box(black_box(lhs) * black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn mul_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
             T::to_nearest();
+            let r = black_box(black_box(lhs) * black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
@@ -80,18 +152,40 @@ impl<T: Num + rmode::EditRoundingMode> RoundDiv for Hardware<T> {
     #[inline(never)]
     fn div_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::upward();
-            let r = lhs / rhs;
-            T::to_nearest();
+            let r = black_box(black_box(lhs) / black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
     #[inline(never)]
     fn div_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::downward();
-            let r = lhs / rhs;
+            let r = black_box(black_box(lhs) / black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn div_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
+            T::toward_zero();
+            let r = black_box(black_box(lhs) / black_box(rhs));
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn div_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
             T::to_nearest();
+            let r = black_box(black_box(lhs) / black_box(rhs));
+            T::set_rounding_state(state);
             r
         }
     }
@@ -101,18 +195,40 @@ impl<T: Num + Sqrt<Output = T> + rmode::EditRoundingMode> RoundSqrt for Hardware
     #[inline(never)]
     fn sqrt_up(lhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::upward();
-            let r = lhs.sqrt();
-            T::to_nearest();
+            let r = black_box(black_box(lhs).sqrt());
+            T::set_rounding_state(state);
             r
         }
     }
     #[inline(never)]
     fn sqrt_down(lhs: Self::Num) -> Self::Num {
         unsafe {
+            let state = T::current_rounding_state();
             T::downward();
-            let r = lhs.sqrt();
+            let r = black_box(black_box(lhs).sqrt());
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn sqrt_toward_zero(lhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
+            T::toward_zero();
+            let r = black_box(black_box(lhs).sqrt());
+            T::set_rounding_state(state);
+            r
+        }
+    }
+    #[inline(never)]
+    fn sqrt_nearest(lhs: Self::Num) -> Self::Num {
+        unsafe {
+            let state = T::current_rounding_state();
             T::to_nearest();
+            let r = black_box(black_box(lhs).sqrt());
+            T::set_rounding_state(state);
             r
         }
     }
@@ -190,6 +306,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn nearest_under_guard() {
+        use roundops::rmode::EditRoundingMode;
+        use roundops::direction::ToNearest;
+        type Near = RoundedNum<ToNearest, f64, Hwrf64>;
+        let mut rng = thread_rng();
+        let mut c = f64::rmode_controler().unwrap();
+        for _ in 0..10000 {
+            let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>() - 0.5);
+            let near = [a + b, a - b, a * b, a / b, a.sqrt()];
+            let _up = c.upward();
+            assert_eq!([Hwrf64::add_nearest(a, b), Hwrf64::sub_nearest(a, b),
+                        Hwrf64::mul_nearest(a, b), Hwrf64::div_nearest(a, b),
+                        Hwrf64::sqrt_nearest(a)],
+                       near);
+            assert_eq!((Near::new(a) + b).extract(), near[0]);
+        }
+        let _up = c.upward();
+        assert_eq!(Hwrf64::add_nearest(1., 1e-30), 1.);
+    }
+
     #[test]
     fn sqrt() {
         let mut rng = thread_rng();
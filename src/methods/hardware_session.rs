@@ -0,0 +1,392 @@
+use core::hint::black_box;
+use core::marker::PhantomData;
+use core::ops::Neg;
+
+use num_traits::Num;
+use float_traits::Sqrt;
+
+use roundops::*;
+use roundops::direction::{Direction, Upward, Downward};
+use roundops::rmode::{EditRoundingMode, SetRoundingMode};
+
+/// Hardware rounding that assumes the rounding mode is already set to `Dir`.
+///
+/// Operations in direction `Dir` are single native operations, and the opposite direction
+/// is obtained by negation, e.g. `add_down(a, b) == -((-a) + (-b))` under `Upward`.
+/// Only the square root in the opposite direction may be one ulp wider than optimal.
+/// Use it inside `session`, under a `RoundingGuard` of direction `Dir`, or through
+/// `compute`, which all switch the mode once for the whole computation.
+#[derive(Clone)]
+pub struct HardwareSession<T: Num + Neg<Output = T> + Clone + EditRoundingMode, Dir: Direction>(
+    PhantomData<fn(T, Dir)>);
+
+impl<T, Dir> HardwareSession<T, Dir>
+    where T: Num + Neg<Output = T> + Clone + EditRoundingMode,
+          Dir: Direction + SetRoundingMode
+{
+    /// Runs `func` with the rounding mode set to `Dir`, and then restores the previous mode.
+    #[inline]
+    pub fn session<O, F: FnOnce() -> O>(func: F) -> O {
+        rmode::scoped::<T, Dir, _, _>(func)
+    }
+}
+
+impl<T, Dir> RoundingMethod for HardwareSession<T, Dir>
+    where T: Num + Neg<Output = T> + Clone + EditRoundingMode,
+          Dir: Direction
+{
+    type HostMethod = rmode::Fixed<Dir>;
+    type Num = T;
+}
+
+// Computes `f` rounding to nearest, and then sets the mode back to `Dir`.
+#[inline(never)]
+fn nearest<T: EditRoundingMode, Dir: SetRoundingMode, F: FnOnce() -> T>(f: F) -> T {
+    unsafe {
+        T::to_nearest();
+        let r = f();
+        Dir::set_rounding_mode::<T>();
+        r
+    }
+}
+
+// `black_box` keeps each operation inside the region where the mode is set.
+macro_rules! impl_session {
+    ($dir:ty, [$add:ident, $sub:ident, $mul:ident, $div:ident, $sqrt:ident],
+     [$nadd:ident, $nsub:ident, $nmul:ident, $ndiv:ident, $nsqrt:ident]) => (
+        impl<T> RoundAdd for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Clone + EditRoundingMode
+        {
+            #[inline]
+            fn $add(a: T, b: T) -> T {
+                black_box(black_box(a) + black_box(b))
+            }
+            #[inline]
+            fn $nadd(a: T, b: T) -> T {
+                -black_box(black_box(-a) + black_box(-b))
+            }
+            #[inline]
+            fn add_nearest(a: T, b: T) -> T {
+                nearest::<T, $dir, _>(|| black_box(black_box(a) + black_box(b)))
+            }
+        }
+
+        impl<T> RoundSub for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Clone + EditRoundingMode
+        {
+            #[inline]
+            fn $sub(a: T, b: T) -> T {
+                black_box(black_box(a) - black_box(b))
+            }
+            #[inline]
+            fn $nsub(a: T, b: T) -> T {
+                -black_box(black_box(b) - black_box(a))
+            }
+            #[inline]
+            fn sub_nearest(a: T, b: T) -> T {
+                nearest::<T, $dir, _>(|| black_box(black_box(a) - black_box(b)))
+            }
+        }
+
+        impl<T> RoundMul for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Clone + EditRoundingMode
+        {
+            #[inline]
+            fn $mul(a: T, b: T) -> T {
+                black_box(black_box(a) * black_box(b))
+            }
+            #[inline]
+            fn $nmul(a: T, b: T) -> T {
+                -black_box(black_box(-a) * black_box(b))
+            }
+            #[inline]
+            fn mul_nearest(a: T, b: T) -> T {
+                nearest::<T, $dir, _>(|| black_box(black_box(a) * black_box(b)))
+            }
+        }
+
+        impl<T> RoundDiv for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Clone + EditRoundingMode
+        {
+            #[inline]
+            fn $div(a: T, b: T) -> T {
+                black_box(black_box(a) / black_box(b))
+            }
+            #[inline]
+            fn $ndiv(a: T, b: T) -> T {
+                -black_box(black_box(-a) / black_box(b))
+            }
+            #[inline]
+            fn div_nearest(a: T, b: T) -> T {
+                nearest::<T, $dir, _>(|| black_box(black_box(a) / black_box(b)))
+            }
+        }
+
+        impl<T> RoundSqrt for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Sqrt<Output = T> + Clone + EditRoundingMode
+        {
+            #[inline]
+            fn $sqrt(a: T) -> T {
+                black_box(black_box(a).sqrt())
+            }
+            // The quotient of `a` and its square root rounded the other way.
+            // Only zero and infinity satisfy `s + s == s`, and they are exact.
+            #[inline]
+            fn $nsqrt(a: T) -> T {
+                let s = Self::$sqrt(a.clone());
+                if black_box(s.clone() + s.clone()) == s {
+                    s
+                } else {
+                    Self::$ndiv(a, s)
+                }
+            }
+            #[inline]
+            fn sqrt_nearest(a: T) -> T {
+                nearest::<T, $dir, _>(|| black_box(black_box(a).sqrt()))
+            }
+        }
+
+        impl<T> RoundedSession for HardwareSession<T, $dir>
+            where T: Num + Neg<Output = T> + Clone + EditRoundingMode
+        {
+            type Num = T;
+
+            #[inline]
+            fn compute<D, I, O, F>(input: I, func: F) -> O::Plain
+                where D: Direction,
+                      I: SessionInput<D, Self>,
+                      O: SessionOutput,
+                      F: FnOnce(I::Rounded) -> O
+            {
+                Self::session(|| func(input.into_rounded()).into_plain())
+            }
+
+            #[inline]
+            fn bounds<I, F>(func: F, input: I)
+                            -> (<F::Output<Downward> as SessionOutput>::Plain,
+                                <F::Output<Upward> as SessionOutput>::Plain)
+                where I: Clone,
+                      F: DirectedFn<Self, I>
+            {
+                Self::session(|| {
+                    (func.call::<Downward>(input.clone()).into_plain(),
+                     func.call::<Upward>(input).into_plain())
+                })
+            }
+        }
+    )
+}
+
+impl_session!(Upward,
+              [add_up, sub_up, mul_up, div_up, sqrt_up],
+              [add_down, sub_down, mul_down, div_down, sqrt_down]);
+impl_session!(Downward,
+              [add_down, sub_down, mul_down, div_down, sqrt_down],
+              [add_up, sub_up, mul_up, div_up, sqrt_up]);
+
+#[cfg(test)]
+mod tests {
+    use core::ops::{Add, Mul};
+    use rand::{Rng, thread_rng};
+    use float_traits::Sqrt;
+
+    use roundops::*;
+    use roundops::direction::{Upward, Downward};
+    use methods::EmulationRegular;
+    use binary128::F128;
+    use utils::FloatSuccPred;
+
+    use super::HardwareSession;
+    use bounds;
+
+    macro_rules! check {
+        ($dir:ty) => ({
+            type Session = HardwareSession<F128, $dir>;
+            type Emu = EmulationRegular<F128>;
+            let mut rng = thread_rng();
+            for _ in 0..10000 {
+                let (a, b) = (F128::from(rng.gen::<f64>()), F128::from(rng.gen::<f64>() - 0.5));
+                let c = Emu::div_up(a, b);
+                let got = Session::session(|| {
+                    [Session::add_up(a, c), Session::add_down(a, c),
+                     Session::sub_up(a, c), Session::sub_down(a, c),
+                     Session::mul_up(a, c), Session::mul_down(a, c),
+                     Session::div_up(a, c), Session::div_down(a, c),
+                     Session::sqrt_up(a), Session::sqrt_down(a)]
+                });
+                let expected = [Emu::add_up(a, c), Emu::add_down(a, c),
+                                Emu::sub_up(a, c), Emu::sub_down(a, c),
+                                Emu::mul_up(a, c), Emu::mul_down(a, c),
+                                Emu::div_up(a, c), Emu::div_down(a, c)];
+                for (x, y) in got.iter().zip(expected.iter()) {
+                    assert_eq!(x.to_bits(), y.to_bits());
+                }
+                let (eup, edown) = (Emu::sqrt_up(a), Emu::sqrt_down(a));
+                assert!(eup <= got[8] && got[8] <= eup.succ());
+                assert!(edown.pred() <= got[9] && got[9] <= edown);
+            }
+        })
+    }
+
+    #[test]
+    fn upward() {
+        check!(Upward);
+    }
+
+    #[test]
+    fn downward() {
+        check!(Downward);
+    }
+
+    #[test]
+    fn symmetric_directions() {
+        type Session = HardwareSession<F128, Upward>;
+        type Emu = EmulationRegular<F128>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b) = (F128::from(rng.gen::<f64>()), F128::from(rng.gen::<f64>() - 0.5));
+            let c = Emu::div_up(a, b);
+            let got = Session::session(|| {
+                [Session::add_nearest(a, c), Session::sub_toward_zero(a, c),
+                 Session::mul_nearest(a, c), Session::div_away_from_zero(a, c),
+                 Session::sqrt_nearest(a), Session::sqrt_toward_zero(a)]
+            });
+            let expected = [a + c, Emu::sub_toward_zero(a, c), a * c,
+                            Emu::div_away_from_zero(a, c), a.sqrt()];
+            for (x, y) in got.iter().zip(expected.iter()) {
+                assert_eq!(x.to_bits(), y.to_bits());
+            }
+            assert!(Emu::sqrt_down(a).pred() <= got[5] && got[5] <= Emu::sqrt_down(a));
+        }
+    }
+
+    #[cfg(feature = "hwrm")]
+    #[test]
+    fn native_f64() {
+        type Session = HardwareSession<f64, Downward>;
+        type Emu = EmulationRegular<f64>;
+        let mut rng = thread_rng();
+        let v = (0..1000).map(|_| rng.gen::<f64>() - 0.5).collect::<Vec<_>>();
+        let (up, down) = Session::session(|| {
+            (v.iter().fold(0., |acc, &x| Session::add_up(acc, Session::mul_up(x, x))),
+             v.iter().fold(0., |acc, &x| Session::add_down(acc, Session::mul_down(x, x))))
+        });
+        assert_eq!(up, v.iter().fold(0., |acc, &x| Emu::add_up(acc, Emu::mul_up(x, x))));
+        assert_eq!(down, v.iter().fold(0., |acc, &x| Emu::add_down(acc, Emu::mul_down(x, x))));
+    }
+
+    #[cfg(feature = "hwrm")]
+    #[test]
+    fn float_nearest() {
+        use num_traits::Float;
+        use num_traits::real::Real;
+        use roundops::direction::ToNearest;
+
+        fn norm<T: Float>(x: T, y: T) -> T {
+            (x * x + y * y).sqrt()
+        }
+        fn rsqrt<T: Real>(x: T) -> T {
+            x.sqrt().recip()
+        }
+        // Rounded to nearest by the method although the mode is upward.
+        type Near = RoundedNum<ToNearest, f64, HardwareSession<f64, Upward>>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (x, y) = (rng.gen::<f64>(), rng.gen::<f64>());
+            let (n, r) = HardwareSession::<f64, Upward>::session(|| {
+                (norm(Near::new(x), Near::new(y)).extract(), rsqrt(Near::new(x)).extract())
+            });
+            assert_eq!((n, r), (norm(x, y), rsqrt(x)));
+        }
+    }
+
+    #[test]
+    fn compute() {
+        type Up = HardwareSession<F128, Upward>;
+        type Emu = EmulationRegular<F128>;
+        let mut rng = thread_rng();
+        let v = (0..100).map(|_| F128::from(rng.gen::<f64>() - 0.5)).collect::<Vec<_>>();
+        let scale = F128::from(3.);
+        let (sum, [first, last], empty) = Up::compute::<Upward, _, _, _>(
+            (&v[..], [v[0], v[99]]),
+            |(v, [a, b])| (v.iter().fold(a * b, |acc, &x| acc + x / scale), [a / scale, b / scale],
+                           v.is_empty()));
+        let esum = v.iter().fold(Emu::mul_up(v[0], v[99]),
+                                 |acc, &x| Emu::add_up(acc, Emu::div_up(x, scale)));
+        assert_eq!(sum.to_bits(), esum.to_bits());
+        assert_eq!((first.to_bits(), last.to_bits()),
+                   (Emu::div_up(v[0], scale).to_bits(), Emu::div_up(v[99], scale).to_bits()));
+        assert!(!empty);
+        let third = |x: RoundedNum<Downward, F128, _>| Some(x / scale);
+        assert_eq!(Up::compute(F128::from(1.), third).map(F128::to_bits),
+                   Some(Emu::div_down(F128::from(1.), scale).to_bits()));
+        assert_eq!(Emu::compute(F128::from(1.), |x: RoundedNum<Downward, _, _>| x / scale)
+                       .to_bits(),
+                   Emu::div_down(F128::from(1.), scale).to_bits());
+        // Plain numbers come back as they are, next to the rounded ones.
+        let (q, x, n) = Up::compute::<Upward, _, _, _>(
+            (F128::from(1.), 0.5f64),
+            |(x, n)| (x / scale, x.extract(), n.extract() * 4.));
+        assert_eq!((q.to_bits(), x.to_bits(), n),
+                   (Emu::div_up(F128::from(1.), scale).to_bits(), F128::from(1.).to_bits(), 2.));
+    }
+
+    #[test]
+    fn bounds() {
+        // Sum of `1 / (x + k)` for positive `x`, which increases with every rounding.
+        struct Harmonic(usize);
+        impl<M: RoundingMethod<Num = F128>> DirectedFn<M, (F128, F128)> for Harmonic {
+            type Output<D: direction::Direction> = RoundedNum<D, F128, M>;
+            fn call<D: direction::Direction>(&self, (x, one): (F128, F128))
+                                             -> RoundedNum<D, F128, M>
+                where M: RoundArith<D>
+            {
+                let (x, one) = (RoundedNum::<D, _, M>::new(x), RoundedNum::new(one));
+                (0..self.0).fold(RoundedNum::new(F128::from(0.)), |acc, k| {
+                    acc + one / (x + RoundedNum::from(F128::from(k as f64)))
+                })
+            }
+        }
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let input = (F128::from(rng.gen::<f64>()), F128::from(1.));
+            let (lo, hi) = HardwareSession::<F128, Upward>::bounds(Harmonic(50), input);
+            let (elo, ehi) = EmulationRegular::<F128>::bounds(Harmonic(50), input);
+            assert!(lo < hi);
+            assert_eq!((lo.to_bits(), hi.to_bits()), (elo.to_bits(), ehi.to_bits()));
+            let n = 50;
+            let (mlo, mhi) = bounds!(HardwareSession<F128, Downward>, input, move |(x, one)| {
+                (0..n).fold(RoundedNum::new(F128::from(0.)), |acc, k| {
+                    acc + one / (x + RoundedNum::from(F128::from(k as f64)))
+                })
+            });
+            assert_eq!((mlo.to_bits(), mhi.to_bits()), (lo.to_bits(), hi.to_bits()));
+        }
+    }
+
+    #[test]
+    fn calc_with() {
+        fn horner<D: direction::Direction, M: RoundOps<F128>>(v: Vec<RoundedNum<D, F128, M>>)
+                                                           -> Vec<RoundedNum<D, F128, M>>
+            where RoundedNum<D, F128, M>: Copy + Add<Output = RoundedNum<D, F128, M>> +
+                                          Mul<Output = RoundedNum<D, F128, M>>
+        {
+            let x = v[0];
+            let r = v[1..].iter().fold(RoundedNum::new(F128::from(0.)), |acc, &c| acc * x + c);
+            vec![r]
+        }
+        let x = F128::from(1.) / F128::from(3.);
+        let input = vec![x, F128::from(3.), F128::from(-1.), F128::from(0.7)];
+        let (up, down) =
+            (HardwareSession::<F128, Upward>::calc_with::<Upward>(input.clone(), horner)[0],
+             HardwareSession::<F128, Upward>::calc_with::<Downward>(input.clone(), horner)[0]);
+        let (eup, edown) =
+            (EmulationRegular::<F128>::calc_with::<Upward>(input.clone(), horner)[0],
+             EmulationRegular::<F128>::calc_with::<Downward>(input, horner)[0]);
+        assert!(down < up);
+        assert_eq!((up.to_bits(), down.to_bits()), (eup.to_bits(), edown.to_bits()));
+        let (one, tiny) = (F128::from(1.), F128::from(2f64.powi(-120)));
+        assert!(one + tiny == one &&
+                HardwareSession::<F128, Upward>::session(|| one + tiny) > one);
+    }
+}
@@ -1,14 +1,18 @@
 mod hardware;
+mod hardware_session;
 mod emulation;
 mod emulation_unchecked;
 mod succpred;
 mod succpred_unchecked;
 mod roughwrap;
 mod roughwrap_unchecked;
+mod any;
+mod select;
 
 pub use self::hardware::Hardware;
+pub use self::hardware_session::HardwareSession;
 
-pub use self::emulation::EmulationRegular;
+pub use self::emulation::{EmulationRegular, EmulationAuto};
 #[cfg(any(feature = "use-fma", feature = "doc"))]
 pub use self::emulation::EmulationFma;
 #[cfg(not(feature = "use-fma"))]
@@ -29,3 +33,7 @@ pub use self::succpred_unchecked::SuccPredUnchecked;
 
 pub use self::roughwrap::RoughWrapping;
 pub use self::roughwrap_unchecked::RoughWrappingUnchecked;
+
+pub use self::any::{DynRoundOps, Method, AnyMethod, MethodKind, ParseMethodKindError, Selected,
+                    Selectable};
+pub use self::select::{DefaultMethod, DefaultChoice};
@@ -0,0 +1,317 @@
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use float_traits::IEEE754Float;
+
+use roundops::*;
+use utils::fma_available;
+
+#[cfg(any(feature = "fenv", all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+use super::Hardware;
+use super::{EmulationAuto, EmulationRegular, SuccPred};
+
+/// The methods `DefaultMethod` chooses from, in order of preference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DefaultChoice {
+    Hardware,
+    EmulationAuto,
+    EmulationRegular,
+    SuccPred,
+}
+
+impl fmt::Display for DefaultChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DefaultChoice::Hardware => "Hardware",
+            DefaultChoice::EmulationAuto => "EmulationAuto",
+            DefaultChoice::EmulationRegular => "EmulationRegular",
+            DefaultChoice::SuccPred => "SuccPred",
+        })
+    }
+}
+
+/// The best method that works on the running machine and in the environment of the
+/// calling thread.
+///
+/// `Hardware` is preferred if a rounding-mode backend is compiled in, then `EmulationAuto`
+/// if the CPU has a fused multiply-add, then `EmulationRegular`, and each must pass a
+/// known-answer test. These capabilities are probed once in the default environment.
+/// The environment of the thread is checked on the first operation after each change that
+/// `rmode::environment_changed` reports: the emulations need rounding to nearest, and
+/// `SuccPred`, which does not, is the last resort.
+///
+/// # Panics
+///
+/// If subnormals are flushed to zero, as none of the methods is correct then: even the
+/// step of `SuccPred` below `MIN_POSITIVE` is flushed.
+#[derive(Clone)]
+pub struct DefaultMethod<T>(PhantomData<fn(T)>);
+
+// Known-answer test of directed rounding near one and in the subnormal range.
+fn passes<T: IEEE754Float + Clone, M: RoundOps<T> + RoundSqrt>() -> bool {
+    let (one, eps, tiny) = (T::one(), T::eps(), T::unit_underflow());
+    let (two, half) = (T::one() + T::one(), T::one() / (T::one() + T::one()));
+    let (three, x) = (T::one() + T::one() + T::one(), T::one() + T::eps());
+    M::add_up(one.clone(), tiny.clone()) == one.clone() + eps.clone() &&
+    M::add_down(one.clone(), tiny.clone()) == one &&
+    M::sub_down(one.clone(), tiny.clone()) == one.clone() - eps.clone() / two.clone() &&
+    M::sub_up(one.clone(), tiny.clone()) == one &&
+    M::mul_up(x.clone(), x.clone()) == one.clone() + eps.clone() * three.clone() &&
+    M::mul_down(x.clone(), x) == one.clone() + eps.clone() * two.clone() &&
+    M::div_up(one.clone(), three.clone()) - M::div_down(one, three) ==
+    eps.clone() / (two.clone() * two.clone()) &&
+    M::sqrt_up(two.clone()) - M::sqrt_down(two) == eps &&
+    M::mul_up(tiny.clone(), half.clone()) == tiny && M::mul_down(tiny, half) == T::zero()
+}
+
+// Capabilities of the machine, as bits of the caches below.
+const PROBED: u8 = 1;
+const HARDWARE: u8 = 2;
+const AUTO: u8 = 4;
+const REGULAR: u8 = 8;
+
+macro_rules! impl_default_method {
+    ($fxx:ident, $cache:ident) => (
+        static $cache: AtomicU8 = AtomicU8::new(0);
+
+        impl DefaultMethod<$fxx> {
+            // The known-answer tests assume the default environment, which the backend sets.
+            #[cfg(any(feature = "fenv",
+                      all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+            fn probe() -> Option<u8> {
+                use roundops::rmode::EditRoundingMode;
+                unsafe {
+                    let state = $fxx::current_rounding_state();
+                    $fxx::default_environment();
+                    let caps = Self::probe_default(passes::<$fxx, Hardware<$fxx>>());
+                    $fxx::set_rounding_state(state);
+                    Some(caps)
+                }
+            }
+
+            // Without a backend, the tests can only run if the environment is the default.
+            #[cfg(not(any(feature = "fenv",
+                          all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64")))))]
+            fn probe() -> Option<u8> {
+                if rmode::check_environment::<$fxx>().is_ok() {
+                    Some(Self::probe_default(false))
+                } else {
+                    None
+                }
+            }
+
+            fn probe_default(hardware: bool) -> u8 {
+                let mut caps = PROBED;
+                if hardware {
+                    caps |= HARDWARE;
+                }
+                if fma_available() && passes::<$fxx, EmulationAuto<$fxx>>() {
+                    caps |= AUTO;
+                }
+                if passes::<$fxx, EmulationRegular<$fxx>>() {
+                    caps |= REGULAR;
+                }
+                caps
+            }
+
+            fn capabilities() -> u8 {
+                match $cache.load(Ordering::Relaxed) {
+                    0 => {
+                        let caps = Self::probe().unwrap_or(0);
+                        $cache.store(caps, Ordering::Relaxed);
+                        caps
+                    }
+                    caps => caps,
+                }
+            }
+
+            /// The method for the environment of the calling thread, probing the machine on
+            /// the first call. It is cached until the environment changes.
+            ///
+            /// # Panics
+            ///
+            /// If subnormal results or operands are flushed to zero, in any rounding mode.
+            #[inline]
+            pub fn choice() -> DefaultChoice {
+                thread_local! {
+                    static CHOICE: Cell<Option<(u64, DefaultChoice)>> = const { Cell::new(None) };
+                }
+                let epoch = rmode::environment_epoch();
+                match CHOICE.with(Cell::get) {
+                    Some((cached, choice)) if cached == epoch => choice,
+                    _ => {
+                        let choice = Self::check_choice();
+                        CHOICE.with(|cell| cell.set(Some((epoch, choice))));
+                        choice
+                    }
+                }
+            }
+
+            #[inline(never)]
+            fn check_choice() -> DefaultChoice {
+                let caps = Self::capabilities();
+                if let Err(e) = rmode::check_underflow::<$fxx>() {
+                    panic!(concat!("no method of `DefaultMethod<", stringify!($fxx),
+                                   ">` is correct: {}"), e);
+                }
+                match rmode::check_environment::<$fxx>() {
+                    Ok(()) | Err(rmode::EnvironmentError::NotToNearest)
+                        if caps & HARDWARE != 0 => DefaultChoice::Hardware,
+                    Ok(()) if caps & AUTO != 0 => DefaultChoice::EmulationAuto,
+                    Ok(()) if caps & REGULAR != 0 => DefaultChoice::EmulationRegular,
+                    _ => DefaultChoice::SuccPred,
+                }
+            }
+
+            /// Name of the chosen method, e.g. `"EmulationRegular<f64>"`.
+            pub fn describe() -> &'static str {
+                match Self::choice() {
+                    DefaultChoice::Hardware => concat!("Hardware<", stringify!($fxx), ">"),
+                    DefaultChoice::EmulationAuto =>
+                        concat!("EmulationAuto<", stringify!($fxx), ">"),
+                    DefaultChoice::EmulationRegular =>
+                        concat!("EmulationRegular<", stringify!($fxx), ">"),
+                    DefaultChoice::SuccPred => concat!("SuccPred<", stringify!($fxx), ">"),
+                }
+            }
+        }
+
+        impl RoundingMethod for DefaultMethod<$fxx> {
+            type HostMethod = rmode::DefaultRounding;
+            type Num = $fxx;
+        }
+
+        impl_default_method!(@ops $fxx, RoundAdd, add_up, add_down, add_nearest);
+        impl_default_method!(@ops $fxx, RoundSub, sub_up, sub_down, sub_nearest);
+        impl_default_method!(@ops $fxx, RoundMul, mul_up, mul_down, mul_nearest);
+        impl_default_method!(@ops $fxx, RoundDiv, div_up, div_down, div_nearest);
+
+        impl RoundSqrt for DefaultMethod<$fxx> {
+            #[inline]
+            fn sqrt_up(a: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, sqrt_up(a))
+            }
+            #[inline]
+            fn sqrt_down(a: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, sqrt_down(a))
+            }
+            #[inline]
+            fn sqrt_nearest(a: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, sqrt_nearest(a))
+            }
+        }
+
+        impl RoundedSession for DefaultMethod<$fxx> {
+            type Num = $fxx;
+        }
+    );
+    (@ops $fxx:ident, $tr:ident, $up:ident, $down:ident, $nearest:ident) => (
+        impl $tr for DefaultMethod<$fxx> {
+            #[inline]
+            fn $up(a: $fxx, b: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, $up(a, b))
+            }
+            #[inline]
+            fn $down(a: $fxx, b: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, $down(a, b))
+            }
+            // `Hardware` rounds to nearest in any direction.
+            #[inline]
+            fn $nearest(a: $fxx, b: $fxx) -> $fxx {
+                impl_default_method!(@dispatch $fxx, $nearest(a, b))
+            }
+        }
+    );
+    (@dispatch $fxx:ident, $op:ident($($arg:ident),+)) => (
+        match Self::choice() {
+            #[cfg(any(feature = "fenv",
+                      all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+            DefaultChoice::Hardware => Hardware::<$fxx>::$op($($arg),+),
+            #[cfg(not(any(feature = "fenv",
+                          all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64")))))]
+            DefaultChoice::Hardware => unreachable!(),
+            DefaultChoice::EmulationAuto => EmulationAuto::<$fxx>::$op($($arg),+),
+            DefaultChoice::EmulationRegular => EmulationRegular::<$fxx>::$op($($arg),+),
+            DefaultChoice::SuccPred => SuccPred::<$fxx>::$op($($arg),+),
+        }
+    );
+}
+
+impl_default_method!(f64, F64_CHOICE);
+impl_default_method!(f32, F32_CHOICE);
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use methods::{EmulationRegular, SuccPred};
+
+    use super::{passes, DefaultChoice, DefaultMethod};
+
+    #[test]
+    fn probing() {
+        assert!(passes::<f64, EmulationRegular<f64>>() && passes::<f32, EmulationRegular<f32>>());
+        assert!(!passes::<f64, SuccPred<f64>>());
+        let choice = DefaultMethod::<f64>::choice();
+        assert_ne!(choice, DefaultChoice::SuccPred);
+        assert_eq!(DefaultMethod::<f64>::choice(), choice);
+        assert_eq!(DefaultMethod::<f64>::describe(), format!("{}<f64>", choice));
+        if cfg!(feature = "hwrm") && cfg!(target_arch = "x86_64") {
+            assert_eq!(choice, DefaultChoice::Hardware);
+        }
+
+        type Def = DefaultMethod<f64>;
+        type Emu = EmulationRegular<f64>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>() - 0.5);
+            assert_eq!([Def::add_up(a, b), Def::sub_down(a, b), Def::mul_up(a, b),
+                        Def::div_down(a, b), Def::sqrt_up(a)],
+                       [Emu::add_up(a, b), Emu::sub_down(a, b), Emu::mul_up(a, b),
+                        Emu::div_down(a, b), Emu::sqrt_up(a)]);
+        }
+        let x = RoundedNum::<direction::Upward, f32, DefaultMethod<f32>>::new(1.) / 3.;
+        assert!(f64::from(x.extract()) > 1. / 3.);
+    }
+
+    #[cfg(any(feature = "fenv",
+              all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64"))))]
+    #[test]
+    fn environment() {
+        use std::panic;
+        use roundops::rmode::EditRoundingMode;
+
+        type Def = DefaultMethod<f64>;
+        let (a, b) = (1. / 3., 0.1);
+        let expected = (EmulationRegular::<f64>::add_up(a, b), Def::describe());
+        let mut c = f64::rmode_controler().unwrap();
+        let (describe, sum) = c.toward_zero_session(|| (Def::describe(), Def::add_up(a, b)));
+        assert_eq!(sum, expected.0);
+        if Def::choice() == DefaultChoice::Hardware {
+            let third = c.upward_session(|| (Def::div_nearest(1., 3.), Def::sqrt_nearest(2.)));
+            assert_eq!(third, (1. / 3., 2f64.sqrt()));
+        }
+        if Def::choice() == DefaultChoice::Hardware {
+            assert_eq!(describe, "Hardware<f64>");
+        } else {
+            assert_eq!(describe, "SuccPred<f64>");
+        }
+        assert_eq!(Def::describe(), expected.1);
+
+        // Subnormals flushed to zero rule out every method, whatever the direction.
+        if cfg!(all(feature = "hwrm", target_arch = "x86_64")) {
+            for &bits in &[0x8000, 0x0040, 0x4000 | 0x8040] {
+                let r = c.to_nearest_session(|| unsafe {
+                    f64::set_rounding_state(f64::current_rounding_state() | bits);
+                    rmode::environment_changed();
+                    panic::catch_unwind(|| Def::mul_up(f64::MIN_POSITIVE, 0.5))
+                });
+                assert!(r.is_err());
+            }
+            assert_eq!(Def::mul_up(f64::MIN_POSITIVE, 0.5), f64::MIN_POSITIVE * 0.5);
+        }
+    }
+}
@@ -139,7 +139,7 @@ impl<T: FloatSuccPred + Infinite + BoundedFloat> RoundDiv for SuccPred<T> {
 impl<T: FloatSuccPred + Infinite + BoundedFloat + Sqrt<Output = T>> RoundSqrt
     for SuccPred<T> {
     fn sqrt_up(a: T) -> T {
-        (a.sqrt().succ())
+        a.sqrt().succ()
     }
     fn sqrt_down(a: T) -> T {
         let r = a.sqrt();
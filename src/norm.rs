@@ -0,0 +1,150 @@
+use core::cmp::Ordering;
+
+use num_traits::{One, Zero};
+use float_traits::{Abs, IEEE754Float, Infinite};
+
+use roundops::*;
+
+// Power-of-two thresholds and scaling factors in the style of Blue's algorithm.
+// Every factor lies in the normal range, so scaling is exact unless an element underflows,
+// in which case the directed multiplication keeps the bound rigorous.
+fn scaling<T: IEEE754Float + Clone>(max: &T) -> (T, T) {
+    let one = || T::Exponent::one();
+    let two = || T::Exponent::one() + T::Exponent::one();
+    let zero = || T::Exponent::one() - T::Exponent::one();
+
+    let tbig = T::radix_powi((T::max_exponent() - T::bit_size() + one()) / two());
+    let tsml = T::radix_powi(zero() - (one() - T::min_exponent()) / two());
+    if *max > tbig {
+        let e = || (T::max_exponent() + T::bit_size()) / two();
+        (T::radix_powi(zero() - e()), T::radix_powi(e()))
+    } else if *max < tsml {
+        let e = || (T::bit_size() - T::min_exponent()) / two();
+        (T::radix_powi(e()), T::radix_powi(zero() - e()))
+    } else {
+        (T::one(), T::one())
+    }
+}
+
+/// Maximum absolute value of the elements. This is exact and needs no rounding.
+pub fn norm_inf<T: IEEE754Float + Clone>(v: &[T]) -> T {
+    let mut max = T::zero();
+    for x in v {
+        let a = x.clone().abs();
+        if a.partial_cmp(&max).is_none() {
+            return a;
+        } else if a > max {
+            max = a;
+        }
+    }
+    max
+}
+
+/// Upper bound of the sum of absolute values.
+pub fn norm1_up<M>(v: &[M::Num]) -> M::Num
+    where M: RoundAdd,
+          M::Num: IEEE754Float + Clone
+{
+    v.iter().fold(M::Num::zero(), |acc, x| M::add_up(acc, x.clone().abs()))
+}
+
+/// Lower bound of the sum of absolute values.
+pub fn norm1_down<M>(v: &[M::Num]) -> M::Num
+    where M: RoundAdd,
+          M::Num: IEEE754Float + Clone
+{
+    v.iter().fold(M::Num::zero(), |acc, x| M::add_down(acc, x.clone().abs()))
+}
+
+/// Upper bound of the Euclidean norm. Elements are scaled by a power of the radix,
+/// so the result overflows only if the norm itself does.
+pub fn norm2_up<M>(v: &[M::Num]) -> M::Num
+    where M: RoundAdd + RoundMul + RoundSqrt,
+          M::Num: IEEE754Float + Clone
+{
+    let max = norm_inf(v);
+    if max.partial_cmp(&M::Num::zero()) != Some(Ordering::Greater) || max.is_infinite() {
+        return max;
+    }
+    let (scale, inv) = scaling(&max);
+    let ssq = v.iter().fold(M::Num::zero(), |acc, x| {
+        let y = M::mul_up(x.clone().abs(), scale.clone());
+        M::add_up(acc, M::mul_up(y.clone(), y))
+    });
+    M::mul_up(M::sqrt_up(ssq), inv)
+}
+
+/// Lower bound of the Euclidean norm.
+pub fn norm2_down<M>(v: &[M::Num]) -> M::Num
+    where M: RoundAdd + RoundMul + RoundSqrt,
+          M::Num: IEEE754Float + Clone
+{
+    let max = norm_inf(v);
+    if max.partial_cmp(&M::Num::zero()) != Some(Ordering::Greater) || max.is_infinite() {
+        return max;
+    }
+    let (scale, inv) = scaling(&max);
+    let ssq = v.iter().fold(M::Num::zero(), |acc, x| {
+        let y = M::mul_down(x.clone().abs(), scale.clone());
+        M::add_down(acc, M::mul_down(y.clone(), y))
+    });
+    M::mul_down(M::sqrt_down(ssq), inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use methods::{EmulationRegular, SuccPred};
+
+    use super::*;
+
+    fn reference(v: &[f32]) -> f64 {
+        v.iter().map(|&x| x as f64 * x as f64).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn random_f32() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let len = rng.gen_range(1, 20);
+            let v = (0..len)
+                .map(|_| rng.gen::<f32>() - 0.5)
+                .collect::<Vec<_>>();
+            let r = reference(&v);
+            let (up, down) = (norm2_up::<EmulationRegular<f32>>(&v),
+                              norm2_down::<EmulationRegular<f32>>(&v));
+            assert!(down as f64 <= r * (1. + 1e-12) && r * (1. - 1e-12) <= up as f64);
+            assert!((up - down) as f64 <= r * 8. * f32::EPSILON as f64);
+            let (up, down) = (norm2_up::<SuccPred<f32>>(&v), norm2_down::<SuccPred<f32>>(&v));
+            assert!(down as f64 <= r * (1. + 1e-12) && r * (1. - 1e-12) <= up as f64);
+        }
+    }
+
+    #[test]
+    fn extreme_scale() {
+        type Emuf64 = EmulationRegular<f64>;
+        for &(x, n) in &[(1e300, 2.), (1e-300, 2.), (5e-324, 2.), (f64::MAX, 2.)] {
+            let v = vec![x; 4];
+            let (up, down) = (norm2_up::<Emuf64>(&v), norm2_down::<Emuf64>(&v));
+            assert!(down <= up && down > 0.);
+            assert!(down <= x * n && x * n <= up);
+            assert!(down.is_finite());
+        }
+        assert!(norm2_up::<Emuf64>(&[3e200, 4e200]) >= 5e200);
+        assert!(norm2_down::<Emuf64>(&[3e-200, 4e-200]) <= 5e-200);
+        assert!(norm2_up::<Emuf64>(&[0., -0.]) == 0.);
+        assert!(norm2_up::<Emuf64>(&[1., f64::NAN]).is_nan());
+    }
+
+    #[test]
+    fn norm1_inf() {
+        type Emuf64 = EmulationRegular<f64>;
+        let v = [0.1, -0.2, 0.3];
+        assert!(norm_inf(&v) == 0.3);
+        assert!(norm1_down::<Emuf64>(&v) <= 0.6 && 0.6 <= norm1_up::<Emuf64>(&v));
+        assert!(norm1_up::<Emuf64>(&[f64::MAX, f64::MAX]).is_infinite());
+        assert!(norm1_down::<Emuf64>(&[f64::MAX, f64::MAX]) ==
+                f64::MAX);
+    }
+}
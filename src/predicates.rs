@@ -0,0 +1,336 @@
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::ops::{Add, Sub, Mul};
+
+use num_traits::Zero;
+use float_traits::IEEE754Float;
+
+use roundops::*;
+use expansion::Expansion;
+
+// Interval enclosure evaluated with the directed operations of `M`.
+// It serves as the floating-point filter: if zero is excluded, the sign is certain.
+struct Enclosure<M: RoundingMethod>(M::Num, M::Num, PhantomData<M>);
+
+impl<M: RoundingMethod> Clone for Enclosure<M>
+    where M::Num: Clone
+{
+    fn clone(&self) -> Self {
+        Enclosure(self.0.clone(), self.1.clone(), PhantomData)
+    }
+}
+
+impl<M: RoundingMethod> Enclosure<M>
+    where M::Num: IEEE754Float + Clone
+{
+    fn point(x: M::Num) -> Self {
+        Enclosure(x.clone(), x, PhantomData)
+    }
+
+    fn sign(&self) -> Option<Ordering> {
+        if self.0 > M::Num::zero() {
+            Some(Ordering::Greater)
+        } else if self.1 < M::Num::zero() {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
+}
+
+impl<M: RoundAdd> Add for Enclosure<M>
+    where M::Num: IEEE754Float + Clone
+{
+    type Output = Enclosure<M>;
+    fn add(self, rhs: Enclosure<M>) -> Enclosure<M> {
+        Enclosure(M::add_down(self.0, rhs.0), M::add_up(self.1, rhs.1), PhantomData)
+    }
+}
+
+impl<M: RoundSub> Sub for Enclosure<M>
+    where M::Num: IEEE754Float + Clone
+{
+    type Output = Enclosure<M>;
+    fn sub(self, rhs: Enclosure<M>) -> Enclosure<M> {
+        Enclosure(M::sub_down(self.0, rhs.1), M::sub_up(self.1, rhs.0), PhantomData)
+    }
+}
+
+impl<M: RoundMul> Mul for Enclosure<M>
+    where M::Num: IEEE754Float + Clone
+{
+    type Output = Enclosure<M>;
+    fn mul(self, rhs: Enclosure<M>) -> Enclosure<M> {
+        let (a, b, c, d) = (self.0, self.1, rhs.0, rhs.1);
+        let lo = [M::mul_down(a.clone(), d.clone()),
+                  M::mul_down(b.clone(), c.clone()),
+                  M::mul_down(b.clone(), d.clone())]
+            .iter()
+            .fold(M::mul_down(a.clone(), c.clone()),
+                  |m, x| if *x < m { x.clone() } else { m });
+        let hi = [M::mul_up(a.clone(), d.clone()),
+                  M::mul_up(b.clone(), c.clone()),
+                  M::mul_up(b, d)]
+            .iter()
+            .fold(M::mul_up(a, c), |m, x| if *x > m { x.clone() } else { m });
+        Enclosure(lo, hi, PhantomData)
+    }
+}
+
+trait Ring: Clone + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> {}
+
+impl<R: Clone + Add<Output = R> + Sub<Output = R> + Mul<Output = R>> Ring for R {}
+
+fn orient2d_det<R: Ring>(pa: [R; 2], pb: [R; 2], pc: [R; 2]) -> R {
+    let [ax, ay] = pa;
+    let [bx, by] = pb;
+    let [cx, cy] = pc;
+    (ax - cx.clone()) * (by - cy.clone()) - (ay - cy) * (bx - cx)
+}
+
+fn orient3d_det<R: Ring>(pa: [R; 3], pb: [R; 3], pc: [R; 3], pd: [R; 3]) -> R {
+    let [dx, dy, dz] = pd;
+    let [adx, ady, adz] = [pa[0].clone() - dx.clone(),
+                           pa[1].clone() - dy.clone(),
+                           pa[2].clone() - dz.clone()];
+    let [bdx, bdy, bdz] = [pb[0].clone() - dx.clone(),
+                           pb[1].clone() - dy.clone(),
+                           pb[2].clone() - dz.clone()];
+    let [cdx, cdy, cdz] = [pc[0].clone() - dx, pc[1].clone() - dy, pc[2].clone() - dz];
+    adx * (bdy.clone() * cdz.clone() - bdz.clone() * cdy.clone()) +
+    bdx * (cdy * adz.clone() - cdz * ady.clone()) + cdx * (ady * bdz - adz * bdy)
+}
+
+fn incircle_det<R: Ring>(pa: [R; 2], pb: [R; 2], pc: [R; 2], pd: [R; 2]) -> R {
+    let [dx, dy] = pd;
+    let (adx, ady) = (pa[0].clone() - dx.clone(), pa[1].clone() - dy.clone());
+    let (bdx, bdy) = (pb[0].clone() - dx.clone(), pb[1].clone() - dy.clone());
+    let (cdx, cdy) = (pc[0].clone() - dx, pc[1].clone() - dy);
+    let abdet = adx.clone() * bdy.clone() - bdx.clone() * ady.clone();
+    let bcdet = bdx.clone() * cdy.clone() - cdx.clone() * bdy.clone();
+    let cadet = cdx.clone() * ady.clone() - adx.clone() * cdy.clone();
+    let alift = adx.clone() * adx + ady.clone() * ady;
+    let blift = bdx.clone() * bdx + bdy.clone() * bdy;
+    let clift = cdx.clone() * cdx + cdy.clone() * cdy;
+    alift * bcdet + blift * cadet + clift * abdet
+}
+
+fn insphere_det<R: Ring>(pa: [R; 3], pb: [R; 3], pc: [R; 3], pd: [R; 3], pe: [R; 3]) -> R {
+    let lift = |p: [R; 3]| {
+        let [x, y, z] = p;
+        [x - pe[0].clone(), y - pe[1].clone(), z - pe[2].clone()]
+    };
+    let [aex, aey, aez] = lift(pa);
+    let [bex, bey, bez] = lift(pb);
+    let [cex, cey, cez] = lift(pc);
+    let [dex, dey, dez] = lift(pd);
+
+    let ab = aex.clone() * bey.clone() - bex.clone() * aey.clone();
+    let bc = bex.clone() * cey.clone() - cex.clone() * bey.clone();
+    let cd = cex.clone() * dey.clone() - dex.clone() * cey.clone();
+    let da = dex.clone() * aey.clone() - aex.clone() * dey.clone();
+    let ac = aex.clone() * cey.clone() - cex.clone() * aey.clone();
+    let bd = bex.clone() * dey.clone() - dex.clone() * bey.clone();
+
+    let abc = aez.clone() * bc.clone() - bez.clone() * ac.clone() + cez.clone() * ab.clone();
+    let bcd = bez.clone() * cd.clone() - cez.clone() * bd.clone() + dez.clone() * bc;
+    let cda = cez.clone() * da.clone() + dez.clone() * ac + aez.clone() * cd;
+    let dab = dez.clone() * ab + aez.clone() * bd + bez.clone() * da;
+
+    let alift = aex.clone() * aex + aey.clone() * aey + aez.clone() * aez;
+    let blift = bex.clone() * bex + bey.clone() * bey + bez.clone() * bez;
+    let clift = cex.clone() * cex + cey.clone() * cey + cez.clone() * cez;
+    let dlift = dex.clone() * dex + dey.clone() * dey + dez.clone() * dez;
+
+    (dlift * abc - clift * dab) + (blift * cda - alift * bcd)
+}
+
+macro_rules! lift {
+    ($ty:ident, $p:expr, 2) => ([$ty::from_num($p[0].clone()), $ty::from_num($p[1].clone())]);
+    ($ty:ident, $p:expr, 3) => ([$ty::from_num($p[0].clone()),
+                                 $ty::from_num($p[1].clone()),
+                                 $ty::from_num($p[2].clone())]);
+}
+
+trait FromNum<T> {
+    fn from_num(x: T) -> Self;
+}
+
+impl<M: RoundingMethod> FromNum<M::Num> for Enclosure<M>
+    where M::Num: IEEE754Float + Clone
+{
+    fn from_num(x: M::Num) -> Self {
+        Enclosure::point(x)
+    }
+}
+
+impl<T: IEEE754Float + Clone> FromNum<T> for Expansion<T> {
+    fn from_num(x: T) -> Self {
+        Expansion::from(x)
+    }
+}
+
+macro_rules! predicate {
+    ($(#[$attr:meta])* $name:ident, $det:ident, $dim:tt, $deg:tt, $($p:ident),+) => (
+        $(#[$attr])*
+        ///
+        /// The exact fallback, used when the filter cannot decide, multiplies
+        #[doc = concat!("coordinate differences, ", stringify!($deg), " at a time, with \
+                         error-free products, so")]
+        /// its sign is exact only if none of these products underflows or overflows. For
+        /// `f64` this holds when every nonzero coordinate has an ulp of at least
+        #[doc = concat!("`2^(-969/", stringify!($deg), ")` and a magnitude well below `2^(1023/",
+                        stringify!($deg), ")`; coordinates on a common grid,")]
+        /// such as integers or scaled integers, can be rescaled by a power of two into that
+        /// range without changing the sign.
+        pub fn $name<M>($($p: &[M::Num; $dim]),+) -> Ordering
+            where M: RoundAdd + RoundSub + RoundMul,
+                  M::Num: IEEE754Float + Clone
+        {
+            match $det::<Enclosure<M>>($(lift!(Enclosure, $p, $dim)),+).sign() {
+                Some(sign) => sign,
+                None => $det::<Expansion<M::Num>>($(lift!(Expansion, $p, $dim)),+).sign(),
+            }
+        }
+    )
+}
+
+predicate!(
+    /// `Greater` if `pa`, `pb` and `pc` are in counterclockwise order.
+    orient2d, orient2d_det, 2, 2, pa, pb, pc);
+predicate!(
+    /// `Greater` if `pd` lies below the plane through `pa`, `pb` and `pc`,
+    /// where "below" means that they appear counterclockwise when viewed from above.
+    orient3d, orient3d_det, 3, 3, pa, pb, pc, pd);
+predicate!(
+    /// `Greater` if `pd` lies inside the circle through `pa`, `pb` and `pc`,
+    /// which must be in counterclockwise order.
+    incircle, incircle_det, 2, 4, pa, pb, pc, pd);
+predicate!(
+    /// `Greater` if `pe` lies inside the sphere through `pa`, `pb`, `pc` and `pd`,
+    /// which must be positively oriented in the sense of `orient3d`.
+    insphere, insphere_det, 3, 5, pa, pb, pc, pd, pe);
+
+#[cfg(test)]
+mod tests {
+    use core::cmp::Ordering;
+    use rand::{Rng, thread_rng};
+
+    use methods::{EmulationRegular, SuccPred};
+
+    use super::*;
+
+    type Emuf64 = EmulationRegular<f64>;
+
+    fn int<R: Rng>(rng: &mut R, bits: u32) -> i128 {
+        rng.gen_range(-(1i64 << bits), 1i64 << bits) as i128
+    }
+
+    fn float<const N: usize>(p: [i128; N]) -> [f64; N] {
+        p.map(|x| x as f64)
+    }
+
+    fn sign(x: i128) -> Ordering {
+        x.cmp(&0)
+    }
+
+    #[test]
+    fn orient2d_near_degenerate() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (a, c) = ([int(&mut rng, 50), int(&mut rng, 50)],
+                          [int(&mut rng, 50), int(&mut rng, 50)]);
+            let t = rng.gen_range(-4, 5) as i128;
+            let b = [a[0] + (c[0] - a[0]) / 4 * t + int(&mut rng, 1),
+                     a[1] + (c[1] - a[1]) / 4 * t + int(&mut rng, 1)];
+            let det = (a[0] - c[0]) * (b[1] - c[1]) - (a[1] - c[1]) * (b[0] - c[0]);
+            let (fa, fb, fc) = (float(a), float(b), float(c));
+            assert_eq!(orient2d::<Emuf64>(&fa, &fb, &fc), sign(det));
+            assert_eq!(orient2d::<SuccPred<f64>>(&fa, &fb, &fc), sign(det));
+            assert_eq!(orient2d::<Emuf64>(&fb, &fa, &fc), sign(-det));
+        }
+    }
+
+    #[test]
+    fn orient2d_collinear_floats() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let (x, y, z) = (rng.gen::<f64>(), rng.gen::<f64>() * 1e10, rng.gen::<f64>() * 1e-10);
+            assert_eq!(orient2d::<Emuf64>(&[x, x], &[y, y], &[z, z]), Ordering::Equal);
+            assert_eq!(orient2d::<Emuf64>(&[x, -x], &[y, -y], &[z, -z]), Ordering::Equal);
+        }
+        assert_eq!(orient2d::<Emuf64>(&[0., 0.], &[1., 0.], &[0., 1.]), Ordering::Greater);
+    }
+
+    #[test]
+    fn orient3d_near_degenerate() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            let mut p = [[0i128; 3]; 4];
+            for q in p.iter_mut().take(3) {
+                *q = [int(&mut rng, 30), int(&mut rng, 30), int(&mut rng, 30)];
+            }
+            let (s, t) = (rng.gen_range(-2, 3) as i128, rng.gen_range(-2, 3) as i128);
+            p[3] = [0, 1, 2].map(|i| {
+                p[0][i] + (p[1][i] - p[0][i]) * s + (p[2][i] - p[0][i]) * t + int(&mut rng, 1)
+            });
+            let d = |i: usize, k: usize| p[i][k] - p[3][k];
+            let det = d(0, 0) * (d(1, 1) * d(2, 2) - d(1, 2) * d(2, 1)) +
+                      d(1, 0) * (d(2, 1) * d(0, 2) - d(2, 2) * d(0, 1)) +
+                      d(2, 0) * (d(0, 1) * d(1, 2) - d(0, 2) * d(1, 1));
+            let f = p.map(float);
+            assert_eq!(orient3d::<Emuf64>(&f[0], &f[1], &f[2], &f[3]), sign(det));
+            assert_eq!(orient3d::<Emuf64>(&f[1], &f[0], &f[2], &f[3]), sign(-det));
+        }
+    }
+
+    #[test]
+    fn incircle_near_degenerate() {
+        let mut rng = thread_rng();
+        for _ in 0..100000 {
+            // Points near a circle centered on the origin with integer radius.
+            let r = rng.gen_range(1i64 << 20, 1i64 << 24) as i128;
+            let mut p = [[0i128; 2]; 4];
+            for q in p.iter_mut() {
+                let x = rng.gen_range(-r as i64, r as i64) as i128;
+                let y = ((r * r - x * x) as f64).sqrt() as i128;
+                *q = [x, if rng.gen() { y } else { -y }];
+            }
+            let d = |i: usize, k: usize| p[i][k] - p[3][k];
+            let lift = |i: usize| d(i, 0) * d(i, 0) + d(i, 1) * d(i, 1);
+            let det = lift(0) * (d(1, 0) * d(2, 1) - d(2, 0) * d(1, 1)) +
+                      lift(1) * (d(2, 0) * d(0, 1) - d(0, 0) * d(2, 1)) +
+                      lift(2) * (d(0, 0) * d(1, 1) - d(1, 0) * d(0, 1));
+            let f = p.map(float);
+            assert_eq!(incircle::<Emuf64>(&f[0], &f[1], &f[2], &f[3]), sign(det));
+            assert_eq!(incircle::<Emuf64>(&f[1], &f[0], &f[2], &f[3]), sign(-det));
+        }
+    }
+
+    #[test]
+    fn insphere_near_degenerate() {
+        let mut rng = thread_rng();
+        for _ in 0..20000 {
+            // Points near a sphere centered on the origin with integer radius.
+            let r = rng.gen_range(1i64 << 14, 1i64 << 18) as i128;
+            let mut p = [[0i128; 3]; 5];
+            for q in p.iter_mut() {
+                let x = rng.gen_range(-r as i64, r as i64) as i128;
+                let y = rng.gen_range(-r as i64, r as i64) as i128 / 2;
+                let z = ((r * r - x * x - y * y).max(0) as f64).sqrt() as i128;
+                *q = [x, y, if rng.gen() { z } else { -z }];
+            }
+            let d = |i: usize, k: usize| p[i][k] - p[4][k];
+            let m2 = |i: usize, j: usize| d(i, 0) * d(j, 1) - d(j, 0) * d(i, 1);
+            let m3 = |i: usize, j: usize, k: usize| {
+                d(i, 2) * m2(j, k) - d(j, 2) * m2(i, k) + d(k, 2) * m2(i, j)
+            };
+            let lift = |i: usize| d(i, 0) * d(i, 0) + d(i, 1) * d(i, 1) + d(i, 2) * d(i, 2);
+            let det = (lift(3) * m3(0, 1, 2) - lift(2) * m3(0, 1, 3)) +
+                      (lift(1) * m3(0, 2, 3) - lift(0) * m3(1, 2, 3));
+            let f = p.map(float);
+            assert_eq!(insphere::<Emuf64>(&f[0], &f[1], &f[2], &f[3], &f[4]), sign(det));
+            assert_eq!(insphere::<Emuf64>(&f[1], &f[0], &f[2], &f[3], &f[4]), sign(-det));
+        }
+    }
+}
@@ -1,145 +1,279 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::iter::{Sum, Product};
 use core::marker::PhantomData;
-use core::ops::{Neg, Add, Sub, Mul, Div};
+use core::num::FpCategory;
+use core::ops::{Neg, Add, Sub, Mul, Div, Rem, AddAssign, SubAssign, MulAssign, DivAssign};
 
+use num_traits::{Zero, One, Num, Signed, Bounded, Float, NumCast, ToPrimitive};
+use float_traits::{IEEE754Float, Sqrt};
+
+#[allow(clippy::missing_safety_doc)]
 pub mod rmode {
+    use core::cell::Cell;
+    use core::fmt;
+    use core::hint::black_box;
+    use core::marker::PhantomData;
+    use std::error::Error;
+    use std::thread::{Scope, ScopedJoinHandle};
+
+    use num_traits::Num;
+    use float_traits::{Sqrt, IEEE754Float};
+
     #[cfg(target_env = "msvc")]
     extern "C" {
         fn _controlfp_s(current: *mut u32, new: u32, mask: u32) -> u32;
     }
 
+    use super::{RoundingMethod, RoundedSession, RoundedNum, SessionInput, SessionOutput};
+    use super::direction::Direction;
+
     pub trait NativeRoundingMode {}
 
+    /// Requires the default environment: rounding to nearest with gradual underflow.
     #[derive(Clone)]
     pub enum DefaultRounding {}
 
     impl NativeRoundingMode for DefaultRounding {}
 
+    /// Switches the rounding mode by itself for each operation, and then restores the
+    /// previous state.
     #[derive(Clone)]
     pub enum Switchable {}
 
     impl NativeRoundingMode for Switchable {}
 
+    /// Requires the rounding mode to have been set to `Dir` by the caller.
+    #[derive(Clone)]
+    pub struct Fixed<Dir>(PhantomData<Dir>);
+
+    impl<Dir> NativeRoundingMode for Fixed<Dir> {}
+
+    /// Region of a `RoundingGuard` forcing the default environment.
+    #[derive(Clone)]
+    pub enum DefaultEnvironment {}
+
+    /// Host method whose rounding methods give correct results in `Region`, which is
+    /// either a direction of the rounding mode or `DefaultEnvironment`.
+    ///
+    /// It is checked only where a guard is given the method to run, by
+    /// `RoundingGuard::calc_with` and `RoundingGuard::compute`. The closures of the
+    /// `*_session` functions, code holding a `ModeToken` and `RoundedSession::calc_with`
+    /// can still call any method, e.g. `EmulationRegular` under `upward_session`; the
+    /// `checked_*` functions of `RoundedSession` detect that at run time instead.
+    pub trait HostedIn<Region>: NativeRoundingMode {}
+
+    impl HostedIn<DefaultEnvironment> for Switchable {}
+
+    impl<Dir: Direction> HostedIn<Dir> for Switchable {}
+
+    impl HostedIn<DefaultEnvironment> for DefaultRounding {}
+
+    impl<Dir: Direction> HostedIn<Dir> for Fixed<Dir> {}
+
+    thread_local! {
+        // Whether the thread has a live controler, of any type since types may share the
+        // hardware state, e.g. `f32` and `f64` share MXCSR.
+        static CONTROLER_ALIVE: Cell<bool> = const { Cell::new(false) };
+        // Number of changes of the environment of the thread, which invalidate caches.
+        static ENVIRONMENT_EPOCH: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// Tells the caches of the calling thread, e.g. the choice of `DefaultMethod`, that its
+    /// environment has changed. Controlers, guards and sessions call it themselves; code
+    /// changing the environment otherwise, e.g. through `set_rounding_state`, must call it.
+    #[inline]
+    pub fn environment_changed() {
+        let _ = ENVIRONMENT_EPOCH.try_with(|epoch| epoch.set(epoch.get().wrapping_add(1)));
+    }
+
+    /// Counter which `environment_changed` increments.
+    #[inline]
+    pub(crate) fn environment_epoch() -> u64 {
+        ENVIRONMENT_EPOCH.with(Cell::get)
+    }
+
+    // The right to change the rounding mode of the thread, held by its controler.
+    #[derive(Debug)]
+    struct Slot;
+
+    impl Slot {
+        fn take() -> Option<Slot> {
+            if CONTROLER_ALIVE.with(|alive| alive.replace(true)) {
+                None
+            } else {
+                Some(Slot)
+            }
+        }
+    }
+
+    impl Drop for Slot {
+        fn drop(&mut self) {
+            let _ = CONTROLER_ALIVE.try_with(|alive| alive.set(false));
+        }
+    }
+
     pub trait EditRoundingMode: Sized {
         type RoundingState: Clone;
 
-        fn rmode_controler() -> Result<RoundingModeControler<Self>, ()>;
+        /// The controler of the calling thread, or `Err` while another one is alive.
+        #[allow(clippy::result_unit_err)]
+        #[inline]
+        fn rmode_controler() -> Result<RoundingModeControler<Self>, ()> {
+            let slot = Slot::take().ok_or(())?;
+            Ok(RoundingModeControler {
+                initial_state: Self::current_rounding_state(),
+                _slot: slot,
+                _thread: PhantomData,
+            })
+        }
         fn current_rounding_state() -> Self::RoundingState;
-        unsafe fn set_rounding_state(Self::RoundingState);
+        unsafe fn set_rounding_state(state: Self::RoundingState);
         unsafe fn upward();
         unsafe fn downward();
         unsafe fn to_nearest();
         unsafe fn toward_zero();
+
+        /// Sets rounding to nearest with gradual underflow, the environment assumed by
+        /// `DefaultRounding` methods.
+        #[inline]
+        unsafe fn default_environment() {
+            Self::to_nearest()
+        }
+    }
+
+    /// Deviation of the floating-point environment from the one assumed by
+    /// `DefaultRounding` methods.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum EnvironmentError {
+        NotToNearest,
+        FlushToZero,
+        DenormalsAreZero,
+    }
+
+    impl fmt::Display for EnvironmentError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(match *self {
+                EnvironmentError::NotToNearest => "rounding mode is not to nearest",
+                EnvironmentError::FlushToZero => "subnormal results are flushed to zero",
+                EnvironmentError::DenormalsAreZero => "subnormal operands are treated as zero",
+            })
+        }
+    }
+
+    impl Error for EnvironmentError {}
+
+    /// Checks by probing arithmetic of `T` that the current environment rounds to nearest
+    /// and supports gradual underflow.
+    #[inline(never)]
+    pub fn check_environment<T: IEEE754Float + Clone>() -> Result<(), EnvironmentError> {
+        let (one, eps) = (black_box(T::one()), black_box(T::eps()));
+        let two = T::one() + T::one();
+        let delta = eps.clone() / two.clone() + eps.clone() / (two.clone() * two);
+        if black_box(one.clone() + delta.clone()) != T::one() + T::eps() ||
+           black_box(-one.clone() - delta) != -T::one() - T::eps() {
+            return Err(EnvironmentError::NotToNearest);
+        }
+        check_underflow::<T>()
+    }
+
+    /// Checks by probing arithmetic of `T` that subnormal results and operands are kept,
+    /// whatever the rounding mode: both probes are exact in every direction.
+    #[inline(never)]
+    pub fn check_underflow<T: IEEE754Float + Clone>() -> Result<(), EnvironmentError> {
+        let eps = black_box(T::eps());
+        let (min, unit) = (black_box(T::min_positive()), black_box(T::unit_underflow()));
+        if black_box(min - unit.clone()) == T::zero() {
+            return Err(EnvironmentError::FlushToZero);
+        }
+        if black_box(unit / eps) != T::min_positive() {
+            return Err(EnvironmentError::DenormalsAreZero);
+        }
+        Ok(())
     }
 
-    #[cfg(all(feature = "hwrm", any(target_arch = "x86", target_arch = "x86_64")))]
+    #[cfg(all(feature = "hwrm",
+              any(target_arch = "x86_64", all(target_arch = "x86", target_feature = "sse2"))))]
     mod rmodelocal {
-        extern crate stdsimd;
-        #[cfg(target_feature = "sse2")]
-        use self::stdsimd::vendor::{_MM_GET_ROUNDING_MODE, _MM_SET_ROUNDING_MODE, _MM_ROUND_DOWN,
-                                    _MM_ROUND_NEAREST, _MM_ROUND_TOWARD_ZERO, _MM_ROUND_UP};
-        use super::{EditRoundingMode,RoundingModeControler};
+        use core::arch::asm;
+        use super::EditRoundingMode;
+        use flags::{ExceptionFlags, ExceptionStatus};
+
+        // Rounding control field of the MXCSR register
+        const RC_MASK: u32 = 0x6000;
+        const RC_NEAREST: u32 = 0x0000;
+        const RC_DOWN: u32 = 0x2000;
+        const RC_UP: u32 = 0x4000;
+        const RC_TOWARD_ZERO: u32 = 0x6000;
+        // Flush-to-zero and denormals-are-zero bits
+        const FTZ: u32 = 0x8000;
+        const DAZ: u32 = 0x0040;
+        // Exception flags of the MXCSR register, including the denormal operand flag
+        const FLAGS_MASK: u32 = 0x003F;
+
+        #[inline]
+        fn get_mxcsr() -> u32 {
+            let mut csr = 0u32;
+            unsafe { asm!("stmxcsr [{}]", in(reg) &mut csr, options(nostack, preserves_flags)) };
+            csr
+        }
+
+        #[inline]
+        unsafe fn set_mxcsr(csr: u32) {
+            asm!("ldmxcsr [{}]", in(reg) &csr, options(nostack, preserves_flags));
+        }
+
+        #[inline]
+        unsafe fn set_rounding_control(rc: u32) {
+            set_mxcsr((get_mxcsr() & !RC_MASK) | rc);
+        }
+
         macro_rules! impl_rmode {
             ($type:ty) => (
                 impl EditRoundingMode for $type {
-                    // "On x86, the x87 FPU is used for float operations if the SSE/SSE2 extensions
-                    //  are not available."
-                    // cited from: https://github.com/rust-lang/rust/blob/master/src/libcore/num/dec2flt/algorithm.rs#L41
-                    #[cfg(target_feature = "-sse2")]
-                    // FPU Control Word
-                    type RoundingState = u16;
-                    #[cfg(target_feature = "sse2")]
-                    // MXCSR register
+                    // rounding control, FTZ and DAZ bits of MXCSR, which is used for both
+                    // f32 and f64
                     type RoundingState = u32;
 
-                    #[inline]
-                    fn rmode_controler() -> Result<RoundingModeControler<Self>, ()> {
-                        #[cfg(target_feature = "-sse2")]
-                        {
-                            Ok(RoundingModeControler {
-                                initial_state: {
-                                    let cw = 0u16;
-                                    unsafe { asm!("fnstcw $0" : "=*m" (&cw) ::: "volatile") };
-                                    cw
-                                }
-                            })
-                        }
-                        #[cfg(target_feature = "sse2")]
-                        {
-                            Ok(RoundingModeControler {
-                                initial_state: unsafe{_MM_GET_ROUNDING_MODE()}
-                            })
-                        }
-                    }
                     #[inline]
                     fn current_rounding_state() -> Self::RoundingState {
-                        #[cfg(target_feature = "-sse2")]
-                        {
-                            let cw = 0u16;
-                            unsafe { asm!("fnstcw $0" : "=*m" (&cw) ::: "volatile") };
-                            cw
-                        }
-                        #[cfg(target_feature = "sse2")]
-                        unsafe{ _MM_GET_ROUNDING_MODE() }
+                        get_mxcsr() & (RC_MASK | FTZ | DAZ)
                     }
                     #[inline]
-                    unsafe fn set_rounding_state(state: Self::RoundingState){
-                        #[cfg(target_feature = "-sse2")]
-                        asm!("fldcw $0"
-                            :
-                            : "m" ((Self::current_rounding_state() & 0xF3FF ) | (state & 0x0C00 ))
-                            :
-                            : "volatile"
-                        );
-                        #[cfg(target_feature = "sse2")]
-                        _MM_SET_ROUNDING_MODE(state);
+                    unsafe fn set_rounding_state(state: Self::RoundingState) {
+                        let mask = RC_MASK | FTZ | DAZ;
+                        set_mxcsr((get_mxcsr() & !mask) | (state & mask));
                     }
                     #[inline]
                     unsafe fn upward() {
-                        #[cfg(target_feature = "-sse2")]
-                        asm!("fldcw $0"
-                            :
-                            : "m" ((self.initial_state & 0xF3FF ) | 0x0200 )
-                            :
-                            : "volatile"
-                        );
-                        #[cfg(target_feature = "sse2")]
-                        _MM_SET_ROUNDING_MODE(_MM_ROUND_UP);
+                        set_rounding_control(RC_UP);
                     }
                     #[inline]
                     unsafe fn downward() {
-                        #[cfg(target_feature = "-sse2")]
-                        asm!("fldcw $0"
-                            :
-                            : "m" ((self.initial_state & 0xF3FF ) | 0x0100 )
-                            :
-                            : "volatile"
-                        );
-                        #[cfg(target_feature = "sse2")]
-                        _MM_SET_ROUNDING_MODE(_MM_ROUND_DOWN);
+                        set_rounding_control(RC_DOWN);
                     }
                     #[inline]
                     unsafe fn to_nearest() {
-                        #[cfg(target_feature = "-sse2")]
-                        asm!("fldcw $0"
-                            :
-                            : "m" ((self.initial_state & 0xF3FF ) | 0x0000 )
-                            :
-                            : "volatile"
-                        );
-                        #[cfg(target_feature = "sse2")]
-                        _MM_SET_ROUNDING_MODE(_MM_ROUND_NEAREST);
+                        set_rounding_control(RC_NEAREST);
                     }
                     #[inline]
                     unsafe fn toward_zero() {
-                        #[cfg(target_feature = "-sse2")]
-                        asm!("fldcw $0"
-                            :
-                            : "m" ((self.initial_state & 0xF3FF ) | 0x0300 )
-                            :
-                            : "volatile"
-                        );
-                        #[cfg(target_feature = "sse2")]
-                        _MM_SET_ROUNDING_MODE(_MM_ROUND_TOWARD_ZERO);
+                        set_rounding_control(RC_TOWARD_ZERO);
+                    }
+                    #[inline]
+                    unsafe fn default_environment() {
+                        set_mxcsr(get_mxcsr() & !(RC_MASK | FTZ | DAZ));
+                    }
+                }
+
+                impl ExceptionStatus for $type {
+                    #[inline]
+                    fn exception_flags() -> ExceptionFlags {
+                        ExceptionFlags::from_x86(get_mxcsr())
+                    }
+                    #[inline]
+                    fn clear_exception_flags() {
+                        unsafe { set_mxcsr(get_mxcsr() & !FLAGS_MASK) }
                     }
                 }
             )
@@ -148,82 +282,167 @@ pub mod rmode {
         impl_rmode!(f32);
     }
 
+    /// Direction of rounding that can be set as the rounding mode of the environment.
+    pub trait SetRoundingMode {
+        unsafe fn set_rounding_mode<S: EditRoundingMode>();
+    }
+
+    impl SetRoundingMode for super::direction::Upward {
+        #[inline]
+        unsafe fn set_rounding_mode<S: EditRoundingMode>() {
+            S::upward()
+        }
+    }
+
+    impl SetRoundingMode for super::direction::Downward {
+        #[inline]
+        unsafe fn set_rounding_mode<S: EditRoundingMode>() {
+            S::downward()
+        }
+    }
+
+    impl SetRoundingMode for super::direction::TowardZero {
+        #[inline]
+        unsafe fn set_rounding_mode<S: EditRoundingMode>() {
+            S::toward_zero()
+        }
+    }
+
+    impl SetRoundingMode for super::direction::ToNearest {
+        #[inline]
+        unsafe fn set_rounding_mode<S: EditRoundingMode>() {
+            S::to_nearest()
+        }
+    }
+
+    /// Controler of the rounding mode of the calling thread, which it restores when dropped.
+    ///
+    /// The rounding mode is per-thread state, so neither the controler nor its guards and
+    /// tokens can be sent to another thread, nor held across an `.await` in a future
+    /// that must be `Send`:
+    ///
+    /// ```compile_fail
+    /// use roundops::rmode::EditRoundingMode;
+    /// use roundops::binary128::F128;
+    ///
+    /// fn send<T: Send>(_: T) {}
+    /// let mut c = F128::rmode_controler().unwrap();
+    /// send(c.upward());
+    /// ```
+    ///
+    /// Each thread sets up its own mode instead, e.g. with `with_mode` or `spawn_with_mode`.
+    ///
+    /// A thread has at most one controler at a time, whatever its type, so guards of two
+    /// controlers cannot restore each other's modes out of order:
+    ///
+    /// ```
+    /// use roundops::rmode::EditRoundingMode;
+    /// use roundops::binary128::F128;
+    ///
+    /// let c = F128::rmode_controler().unwrap();
+    /// assert!(F128::rmode_controler().is_err());
+    /// drop(c);
+    /// assert!(F128::rmode_controler().is_ok());
+    /// ```
     #[derive(Debug)]
     pub struct RoundingModeControler<S: EditRoundingMode> {
         initial_state: S::RoundingState,
+        _slot: Slot,
+        _thread: PhantomData<*const ()>,
     }
 
     impl<S: EditRoundingMode> RoundingModeControler<S> {
         #[inline]
         pub fn rollback(&self) {
             unsafe { S::set_rounding_state(self.initial_state.clone()) }
+            environment_changed();
+        }
+        #[inline]
+        pub fn guard<Dir: SetRoundingMode>(&mut self) -> RoundingGuard<'_, S, Dir> {
+            RoundingGuard::new(self)
+        }
+        #[inline]
+        pub fn upward(&mut self) -> RoundingGuard<'_, S, super::direction::Upward> {
+            self.guard()
+        }
+        #[inline]
+        pub fn downward(&mut self) -> RoundingGuard<'_, S, super::direction::Downward> {
+            self.guard()
         }
         #[inline(never)]
-        pub unsafe fn upward_session<O, F>(&mut self, func: F) -> O
+        pub fn upward_session<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
-            let state = S::current_rounding_state();
-            S::upward();
-            let r = func();
-            S::set_rounding_state(state);
-            r
+            let _guard = self.upward();
+            func()
         }
         #[inline(never)]
-        pub unsafe fn downward_session<O, F>(&mut self, func: F) -> O
+        pub fn downward_session<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
-            let state = S::current_rounding_state();
-            S::downward();
-            let r = func();
-            S::set_rounding_state(state);
-            r
+            let _guard = self.downward();
+            func()
         }
         #[inline(never)]
-        pub unsafe fn to_nearest_session<O, F>(&mut self, func: F) -> O
+        pub fn to_nearest_session<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
-            let state = S::current_rounding_state();
-            S::to_nearest();
-            let r = func();
-            S::set_rounding_state(state);
-            r
+            let _guard = self.guard::<super::direction::ToNearest>();
+            func()
         }
         #[inline(never)]
-        pub unsafe fn toward_zero_session<O, F>(&mut self, func: F) -> O
+        pub fn toward_zero_session<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
-            let state = S::current_rounding_state();
-            S::toward_zero();
-            let r = func();
-            S::set_rounding_state(state);
-            r
+            let _guard = self.guard::<super::direction::TowardZero>();
+            func()
+        }
+        /// Guard that forces the environment assumed by `DefaultRounding` methods.
+        #[inline]
+        pub fn default_environment(&mut self) -> RoundingGuard<'_, S, DefaultEnvironment> {
+            RoundingGuard::with(self, || unsafe { S::default_environment() })
+        }
+        #[inline(never)]
+        pub fn default_environment_session<O, F>(&mut self, func: F) -> O
+            where F: FnOnce() -> O
+        {
+            let _guard = self.default_environment();
+            func()
         }
+        #[deprecated(note = "leaves the rounding mode changed; use `upward` instead")]
         #[inline(never)]
         pub unsafe fn upward_then<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
             S::upward();
+            environment_changed();
             func()
         }
+        #[deprecated(note = "leaves the rounding mode changed; use `downward` instead")]
         #[inline(never)]
         pub unsafe fn downward_then<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
             S::downward();
+            environment_changed();
             func()
         }
+        #[deprecated(note = "leaves the rounding mode changed; use `to_nearest_session` instead")]
         #[inline(never)]
         pub unsafe fn to_nearest_then<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
             S::to_nearest();
+            environment_changed();
             func()
         }
+        #[deprecated(note = "leaves the rounding mode changed; use `toward_zero_session` instead")]
         #[inline(never)]
         pub unsafe fn toward_zero_then<O, F>(&mut self, func: F) -> O
             where F: FnOnce() -> O
         {
             S::toward_zero();
+            environment_changed();
             func()
         }
     }
@@ -231,166 +450,1762 @@ pub mod rmode {
     impl<S: EditRoundingMode> Drop for RoundingModeControler<S> {
         fn drop(&mut self) {
             unsafe {
-                let _ = S::set_rounding_state(self.initial_state.clone());
+                S::set_rounding_state(self.initial_state.clone());
             };
+            environment_changed();
         }
     }
 
-    #[cfg(feature = "hwrm")]
-    #[test]
-    fn rf64() {
-        use roundops::rmode::*;
-        let mut c = f64::rmode_controler().unwrap();
-        let x = 0.1;
-        let y = 10.7;
+    // Restores the state it was created with when dropped, also during unwinding.
+    struct Restore<S: EditRoundingMode>(S::RoundingState);
 
-        let v = vec![1., 10., 3146136.314, 6136.1346, 5367.67467, -134562.4537];
-        assert!(unsafe { c.upward_session(|| v.iter().sum::<f64>()) } >
-                unsafe { c.downward_session(|| v.iter().sum::<f64>()) });
-        println!("{} > {}",
-                 unsafe { c.upward_session(|| v.iter().sum::<f64>()) },
-                 unsafe { c.downward_session(|| v.iter().sum::<f64>()) });
-        assert!(unsafe { c.upward_session(|| x + y) } > unsafe { c.downward_session(|| x + y) });
+    impl<S: EditRoundingMode> Drop for Restore<S> {
+        fn drop(&mut self) {
+            unsafe { S::set_rounding_state(self.0.clone()) }
+            environment_changed();
+        }
     }
-}
 
-pub trait RoundingMethod {
-    type HostMethod: rmode::NativeRoundingMode;
-    type Num;
-}
+    /// Runs `func` with the rounding mode of `S` set to `Dir`, and then restores the state
+    /// it found. It needs no controler, so it also works under the guards of one.
+    #[inline(never)]
+    pub(crate) fn scoped<S, Dir, O, F>(func: F) -> O
+        where S: EditRoundingMode,
+              Dir: SetRoundingMode,
+              F: FnOnce() -> O
+    {
+        let _restore = Restore::<S>(S::current_rounding_state());
+        unsafe { Dir::set_rounding_mode::<S>() };
+        environment_changed();
+        func()
+    }
 
-pub trait RoundAdd: RoundingMethod {
-    fn add_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-    fn add_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-}
+    /// Sets the rounding mode to `Dir` while it lives, and restores the previous mode when
+    /// dropped, also during unwinding.
+    ///
+    /// A guard mutably borrows its controler or the guard it was nested in, so only the
+    /// innermost guard is usable and guards are always restored in reverse order:
+    ///
+    /// ```compile_fail
+    /// use roundops::rmode::EditRoundingMode;
+    /// use roundops::binary128::F128;
+    ///
+    /// let mut c = F128::rmode_controler().unwrap();
+    /// let up = c.upward();
+    /// let down = c.downward();
+    /// drop(up);
+    /// ```
+    pub struct RoundingGuard<'a, S: EditRoundingMode + 'a, Dir> {
+        _controler: &'a mut RoundingModeControler<S>,
+        state: S::RoundingState,
+        _dir: PhantomData<Dir>,
+    }
 
-pub trait RoundSub: RoundingMethod {
-    fn sub_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-    fn sub_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-}
+    impl<'a, S: EditRoundingMode + 'a, Dir> RoundingGuard<'a, S, Dir> {
+        #[inline]
+        fn with<F: FnOnce()>(controler: &'a mut RoundingModeControler<S>, set: F) -> Self {
+            let state = S::current_rounding_state();
+            set();
+            environment_changed();
+            RoundingGuard {
+                _controler: controler,
+                state,
+                _dir: PhantomData,
+            }
+        }
 
-pub trait RoundMul: RoundingMethod {
-    fn mul_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-    fn mul_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-}
+        /// Nested guard, which restores the mode of `self` when dropped.
+        #[inline]
+        pub fn guard<D: SetRoundingMode>(&mut self) -> RoundingGuard<'_, S, D> {
+            RoundingGuard::new(self._controler)
+        }
 
-pub trait RoundDiv: RoundingMethod {
-    fn div_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-    fn div_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
-}
+        /// Nested guard forcing the environment assumed by `DefaultRounding` methods.
+        #[inline]
+        pub fn default_environment(&mut self) -> RoundingGuard<'_, S, DefaultEnvironment> {
+            self._controler.default_environment()
+        }
 
-pub trait RoundSqrt: RoundMul {
-    fn sqrt_up(n: Self::Num) -> Self::Num;
-    fn sqrt_down(n: Self::Num) -> Self::Num;
-}
+        /// `RoundedSession::calc_with` of a method that is correct in the region of the
+        /// guard, as stated by its `HostMethod`.
+        ///
+        /// Methods assuming rounding to nearest are rejected as `M` in a directed region,
+        /// although `func` itself is not checked:
+        ///
+        /// ```compile_fail
+        /// use roundops::*;
+        /// use roundops::rmode::EditRoundingMode;
+        /// use roundops::direction::Upward;
+        /// use roundops::methods::EmulationRegular;
+        /// use roundops::binary128::F128;
+        ///
+        /// fn id(v: Vec<RoundedNum<Upward, F128, EmulationRegular<F128>>>)
+        ///       -> Vec<RoundedNum<Upward, F128, EmulationRegular<F128>>> {
+        ///     v
+        /// }
+        /// let mut c = F128::rmode_controler().unwrap();
+        /// c.upward().calc_with(vec![F128::from(1.)], id);
+        /// ```
+        #[allow(clippy::type_complexity)]
+        #[inline]
+        pub fn calc_with<M, D>(&self,
+                               input: Vec<S>,
+                               func: fn(Vec<RoundedNum<D, S, M>>) -> Vec<RoundedNum<D, S, M>>)
+                               -> Vec<S>
+            where M: RoundedSession<Num = S> + RoundingMethod,
+                  M::HostMethod: HostedIn<Dir>,
+                  D: Direction,
+                  S: Clone
+        {
+            M::calc_with(input, func)
+        }
 
+        /// `RoundedSession::compute` of a method that is correct in the region of the guard.
+        #[inline]
+        pub fn compute<M, D, I, O, F>(&self, input: I, func: F) -> O::Plain
+            where M: RoundedSession<Num = S> + RoundingMethod,
+                  M::HostMethod: HostedIn<Dir>,
+                  D: Direction,
+                  I: SessionInput<D, M>,
+                  O: SessionOutput,
+                  F: FnOnce(I::Rounded) -> O,
+                  S: Clone
+        {
+            M::compute(input, func)
+        }
+    }
 
-pub trait RoundOps<T: Add + Sub + Mul + Div>
-    : RoundAdd<Num = T> + RoundSub<Num = T> + RoundMul<Num = T> + RoundDiv<Num = T>
-    {
-}
+    impl<'a, S: EditRoundingMode + 'a, Dir: SetRoundingMode> RoundingGuard<'a, S, Dir> {
+        #[inline]
+        pub fn new(controler: &'a mut RoundingModeControler<S>) -> Self {
+            RoundingGuard::with(controler, || unsafe { Dir::set_rounding_mode::<S>() })
+        }
+    }
 
-impl<S, T> RoundOps<T> for S
-    where S: RoundAdd<Num = T> + RoundSub<Num = T> + RoundMul<Num = T> + RoundDiv<Num = T>,
-          T: Add + Sub + Mul + Div
-{
-}
+    impl<'a, S: EditRoundingMode + 'a, Dir> Drop for RoundingGuard<'a, S, Dir> {
+        fn drop(&mut self) {
+            unsafe { S::set_rounding_state(self.state.clone()) }
+            environment_changed();
+        }
+    }
 
-pub mod direction {
-    pub trait Direction: Clone {
-        type Inversed: Direction;
+    /// Zero-sized proof that the rounding mode of `S` is `Dir`, obtained from an active guard
+    /// or in `with_mode`.
+    ///
+    /// No other controler can exist on the thread, and `Hardware` operations restore the
+    /// mode they find, so only two things can change the mode under a live token: `unsafe`
+    /// setters, and a `HardwareSession` of another direction running a closure that uses
+    /// the token. The token also borrows its guard, which therefore can be neither dropped
+    /// nor nested over while the token is alive:
+    ///
+    /// ```compile_fail
+    /// use roundops::rmode::EditRoundingMode;
+    /// use roundops::binary128::F128;
+    ///
+    /// let mut c = F128::rmode_controler().unwrap();
+    /// let guard = c.upward();
+    /// let token = guard.token();
+    /// drop(guard);
+    /// token.add(F128::from(1.), F128::from(1e-40));
+    /// ```
+    pub struct ModeToken<'g, S, Dir> {
+        _guard: PhantomData<&'g ()>,
+        _mode: PhantomData<fn(S, Dir)>,
+        _thread: PhantomData<*const ()>,
     }
 
-    #[derive(Clone)]
-    pub enum Upward {}
+    pub type UpwardToken<'g, S> = ModeToken<'g, S, super::direction::Upward>;
+    pub type DownwardToken<'g, S> = ModeToken<'g, S, super::direction::Downward>;
 
-    impl Direction for Upward {
-        type Inversed = Downward;
+    impl<'g, S, Dir> Clone for ModeToken<'g, S, Dir> {
+        fn clone(&self) -> Self {
+            *self
+        }
     }
 
-    #[derive(Clone)]
-    pub enum Downward {}
+    impl<'g, S, Dir> Copy for ModeToken<'g, S, Dir> {}
 
-    impl Direction for Downward {
-        type Inversed = Upward;
+    impl<'a, S: EditRoundingMode + 'a, Dir: SetRoundingMode> RoundingGuard<'a, S, Dir> {
+        #[inline]
+        pub fn token(&self) -> ModeToken<'_, S, Dir> {
+            ModeToken {
+                _guard: PhantomData,
+                _mode: PhantomData,
+                _thread: PhantomData,
+            }
+        }
     }
-}
 
-#[derive(Clone)]
-pub struct RoundedNum<Dir: direction::Direction, Num, Method>(Num, PhantomData<(Dir, Method)>);
-impl<Dir: direction::Direction, Num, Method> RoundedNum<Dir, Num, Method> {
-    #[inline(always)]
-    pub fn new(num: Num) -> Self {
-        RoundedNum(num, PhantomData)
-    }
-    #[inline(always)]
-    pub fn extract(self) -> Num {
-        self.0
+    /// Runs `func` on the calling thread with the rounding mode of `S` set to `Dir`, e.g.
+    /// on each worker of a thread pool.
+    ///
+    /// No controler can be created in `func`, so the mode stays `Dir` for the token.
+    ///
+    /// # Panics
+    ///
+    /// If the thread has a live controler, whose guards could change the mode under the
+    /// token, or is in another `with_mode`. Use a guard of that controler instead.
+    #[inline(never)]
+    pub fn with_mode<S, Dir, O, F>(func: F) -> O
+        where S: EditRoundingMode,
+              Dir: SetRoundingMode,
+              F: for<'g> FnOnce(ModeToken<'g, S, Dir>) -> O
+    {
+        let _slot = Slot::take().expect("`with_mode` called while a controler is alive");
+        scoped::<S, Dir, _, _>(|| {
+            func(ModeToken {
+                _guard: PhantomData,
+                _mode: PhantomData,
+                _thread: PhantomData,
+            })
+        })
     }
-}
 
-impl<D: direction::Direction, N: Neg<Output = N>, M> Neg for RoundedNum<D, N, M> {
-    type Output = RoundedNum<D, N, M>;
-    fn neg(self) -> RoundedNum<D, N, M> {
-        RoundedNum(-self.0, PhantomData)
+    /// Spawns a scoped thread running `func` with the rounding mode of `S` set to `Dir`.
+    pub fn spawn_with_mode<'scope, 'env, S, Dir, O, F>(scope: &'scope Scope<'scope, 'env>,
+                                                       func: F)
+                                                       -> ScopedJoinHandle<'scope, O>
+        where S: EditRoundingMode,
+              Dir: SetRoundingMode,
+              O: Send + 'scope,
+              F: for<'g> FnOnce(ModeToken<'g, S, Dir>) -> O + Send + 'scope
+    {
+        scope.spawn(move || with_mode::<S, Dir, O, F>(func))
     }
-}
 
-macro_rules! impl_rnum_op {
-    ($dir:ty, $op:ident, $rop:ident, $fn:ident, $rfn:ident) => (
-        impl<N: $op, M: $rop<Num = N>> $op<RoundedNum<$dir, N, M>>
-            for RoundedNum<$dir, N, M> {
-            type Output = RoundedNum<$dir, N, M>;
-            #[inline(always)]
-            fn $fn(self, rhs: RoundedNum<$dir, N, M>) -> RoundedNum<$dir, N, M> {
-                RoundedNum(M::$rfn(self.0, rhs.0), PhantomData)
-            }
+    // `black_box` keeps the operations inside the guarded region.
+    impl<'g, S: Num + EditRoundingMode, Dir> ModeToken<'g, S, Dir> {
+        #[inline]
+        pub fn add(self, lhs: S, rhs: S) -> S {
+            black_box(black_box(lhs) + black_box(rhs))
         }
-    )
-}
+        #[inline]
+        pub fn sub(self, lhs: S, rhs: S) -> S {
+            black_box(black_box(lhs) - black_box(rhs))
+        }
+        #[inline]
+        pub fn mul(self, lhs: S, rhs: S) -> S {
+            black_box(black_box(lhs) * black_box(rhs))
+        }
+        #[inline]
+        pub fn div(self, lhs: S, rhs: S) -> S {
+            black_box(black_box(lhs) / black_box(rhs))
+        }
+        #[inline]
+        pub fn sqrt(self, n: S) -> S
+            where S: Sqrt<Output = S>
+        {
+            black_box(black_box(n).sqrt())
+        }
+    }
 
-macro_rules! impl_rnum_sqrt {
-    ($dir:ty, $rfn:ident) => (
-        impl<N: Mul, M: RoundSqrt + RoundMul<Num = N>> RoundedNum<$dir, N, M> {
-            #[inline(always)]
-            pub fn sqrt(self) -> RoundedNum<$dir, N, M> {
-                RoundedNum(M::$rfn(self.0), PhantomData)
+    #[cfg(feature = "hwrm")]
+    #[test]
+    fn rf64() {
+        use roundops::rmode::*;
+        use core::hint::black_box;
+        let mut c = f64::rmode_controler().unwrap();
+        let x = black_box(0.1);
+        let y = black_box(10.7);
+
+        let v = [1., 10., 3146136.314, 6136.1346, 5367.67467, -134562.4537];
+        assert!(c.upward_session(|| v.iter().sum::<f64>()) >
+                c.downward_session(|| v.iter().sum::<f64>()));
+        println!("{} > {}",
+                 c.upward_session(|| v.iter().sum::<f64>()),
+                 c.downward_session(|| v.iter().sum::<f64>()));
+        assert!(c.upward_session(|| x + y) > c.downward_session(|| x + y));
+    }
+
+    #[test]
+    fn guard() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use roundops::rmode::*;
+        use roundops::direction::Downward;
+        use binary128::{F128, Rounding};
+
+        let mut c = F128::rmode_controler().unwrap();
+        {
+            let mut up = c.upward();
+            assert_eq!(F128::current_rounding_state(), Rounding::Upward);
+            {
+                let _down = up.guard::<Downward>();
+                assert_eq!(F128::current_rounding_state(), Rounding::Downward);
             }
+            assert_eq!(F128::current_rounding_state(), Rounding::Upward);
         }
-    )
-}
+        assert_eq!(F128::current_rounding_state(), Rounding::ToNearest);
 
-impl_rnum_op!(direction::Upward, Add, RoundAdd, add, add_up);
-impl_rnum_op!(direction::Upward, Sub, RoundSub, sub, sub_up);
-impl_rnum_op!(direction::Upward, Mul, RoundMul, mul, mul_up);
-impl_rnum_op!(direction::Upward, Div, RoundDiv, div, div_up);
-impl_rnum_op!(direction::Downward, Add, RoundAdd, add, add_down);
-impl_rnum_op!(direction::Downward, Sub, RoundSub, sub, sub_down);
-impl_rnum_op!(direction::Downward, Mul, RoundMul, mul, mul_down);
-impl_rnum_op!(direction::Downward, Div, RoundDiv, div, div_down);
-impl_rnum_sqrt!(direction::Upward, sqrt_up);
-impl_rnum_sqrt!(direction::Downward, sqrt_down);
+        let r = catch_unwind(AssertUnwindSafe(|| {
+            let _up = c.upward();
+            panic!("unwinding through a guard");
+        }));
+        assert!(r.is_err());
+        assert_eq!(F128::current_rounding_state(), Rounding::ToNearest);
 
-#[macro_export]
-macro_rules! rnum_init {
-    (<$dir:path,$n:ty,$m:ty>,($numf:expr $(, $num:expr)+)) => (
-        (RoundedNum::<$dir, $n, $m>::new($numf) $(, RoundedNum::<$dir, $n, $m>::new($num))+)
-    )
-}
+        let (one, three) = (F128::from(1.), F128::from(3.));
+        let (up, down) = (c.upward_session(|| one / three), c.downward_session(|| one / three));
+        assert!(down < up && (one / three == down || one / three == up));
+        assert!(c.toward_zero_session(|| one / three) == down);
+        assert!(c.to_nearest_session(|| one / three) == one / three);
+    }
 
-pub trait RoundedSession: Clone + Sized {
-    type Num: Clone;
-    #[inline]
-    fn calc_with<Dir: direction::Direction>(input: Vec<Self::Num>,
-                                            func: fn(Vec<RoundedNum<Dir, Self::Num, Self>>)
+    #[test]
+    fn singleton() {
+        use std::panic::catch_unwind;
+        use roundops::rmode::*;
+        use roundops::direction::Upward;
+        use binary128::{F128, Rounding};
+
+        let mut c1 = F128::rmode_controler().unwrap();
+        assert!(F128::rmode_controler().is_err());
+        let g1 = c1.upward();
+        assert!(catch_unwind(|| with_mode::<F128, Upward, _, _>(|_| ())).is_err());
+        drop(g1);
+        drop(c1);
+        let r = with_mode::<F128, Upward, _, _>(|_| {
+            (F128::rmode_controler().is_err(), F128::current_rounding_state())
+        });
+        assert_eq!(r, (true, Rounding::Upward));
+        let mut c2 = F128::rmode_controler().unwrap();
+        let _g2 = c2.downward();
+        assert_eq!(F128::current_rounding_state(), Rounding::Downward);
+    }
+
+    #[cfg(all(feature = "hwrm", target_arch = "x86_64"))]
+    #[test]
+    fn singleton_mxcsr() {
+        use roundops::rmode::*;
+
+        // A second controler, e.g. `c2` in `g1 = c1.upward(); g2 = c2.downward();
+        // drop(g1); drop(g2)`, would leave the mode upward. `f32` and `f64` share MXCSR,
+        // so neither can be created while `c1` is alive.
+        let mut c1 = f64::rmode_controler().unwrap();
+        let g1 = c1.upward();
+        assert!(f64::rmode_controler().is_err() && f32::rmode_controler().is_err());
+        drop(g1);
+        drop(c1);
+        assert_eq!((check_environment::<f64>(), check_environment::<f32>()), (Ok(()), Ok(())));
+    }
+
+    #[test]
+    fn environment() {
+        use roundops::rmode::*;
+        use roundops::{RoundedNum, RoundedSession};
+        use roundops::direction::Upward;
+        use methods::EmulationRegular;
+        use binary128::F128;
+
+        type Emu = EmulationRegular<F128>;
+
+        assert_eq!(check_environment::<F128>(), Ok(()));
+        let mut c = F128::rmode_controler().unwrap();
+        {
+            let mut up = c.upward();
+            assert_eq!(check_environment::<F128>(), Err(EnvironmentError::NotToNearest));
+            {
+                let _guard = up.guard::<super::direction::Downward>();
+                assert_eq!(check_environment::<F128>(), Err(EnvironmentError::NotToNearest));
+            }
+        }
+        assert_eq!(c.toward_zero_session(check_environment::<F128>),
+                   Err(EnvironmentError::NotToNearest));
+        let r = {
+            let mut up = c.upward();
+            let _default = up.default_environment();
+            check_environment::<F128>()
+        };
+        assert_eq!(r, Ok(()));
+
+        fn twice(v: Vec<RoundedNum<Upward, F128, Emu>>) -> Vec<RoundedNum<Upward, F128, Emu>> {
+            vec![v[0] + v[0]]
+        }
+        let input = vec![F128::from(0.5)];
+        assert_eq!(Emu::checked_calc_with(input.clone(), twice), Ok(vec![F128::from(1.)]));
+        assert_eq!(c.upward_session(|| Emu::checked_calc_with(input, twice)),
+                   Err(EnvironmentError::NotToNearest));
+    }
+
+    #[cfg(all(feature = "hwrm", target_arch = "x86_64"))]
+    #[test]
+    fn environment_mxcsr() {
+        use roundops::rmode::*;
+
+        let mut c = f64::rmode_controler().unwrap();
+        let modes = [(0x8000, EnvironmentError::FlushToZero),
+                     (0x0040, EnvironmentError::DenormalsAreZero),
+                     (0x2000, EnvironmentError::NotToNearest)];
+        for &(bits, err) in &modes {
+            let r = {
+                let mut nearest = c.guard::<super::direction::ToNearest>();
+                unsafe { f64::set_rounding_state(f64::current_rounding_state() | bits) };
+                let (e64, e32) = (check_environment::<f64>(), check_environment::<f32>());
+                let _default = nearest.default_environment();
+                (e64, e32, check_environment::<f64>())
+            };
+            assert_eq!(r, (Err(err), Err(err), Ok(())));
+            // The subnormal probes do not depend on the direction.
+            let r = c.upward_session(|| unsafe {
+                f64::set_rounding_state(f64::current_rounding_state() | bits);
+                (check_underflow::<f64>(), check_underflow::<f32>())
+            });
+            let under = if err == EnvironmentError::NotToNearest { Ok(()) } else { Err(err) };
+            assert_eq!(r, (under, under));
+        }
+        assert_eq!(check_environment::<f64>(), Ok(()));
+    }
+
+    #[test]
+    fn hosted() {
+        use core::ops::Div;
+        use roundops::rmode::*;
+        use roundops::{RoundedNum, RoundDiv};
+        use roundops::direction::{Direction, Upward, Downward};
+        use methods::{EmulationRegular, Hardware, HardwareSession};
+        use binary128::F128;
+
+        fn third<D: Direction, M>(v: Vec<RoundedNum<D, F128, M>>) -> Vec<RoundedNum<D, F128, M>>
+            where RoundedNum<D, F128, M>: Copy + Div<Output = RoundedNum<D, F128, M>>
+        {
+            vec![v[0] / v[1]]
+        }
+        type Emu = EmulationRegular<F128>;
+        let input = vec![F128::from(1.), F128::from(3.)];
+        let mut c = F128::rmode_controler().unwrap();
+        let up = c.upward()
+            .calc_with::<HardwareSession<F128, Upward>, Upward>(input.clone(), third)[0];
+        let down = c.downward()
+            .calc_with::<HardwareSession<F128, Downward>, Downward>(input.clone(), third)[0];
+        let mut up_guard = c.upward();
+        let emu = up_guard.default_environment().calc_with::<Emu, Upward>(input.clone(), third)[0];
+        assert_eq!(up.to_bits(), emu.to_bits());
+        assert_eq!(F128::current_rounding_state(), ::binary128::Rounding::Upward);
+        let hw = Hardware::<F128>::div_down(input[0], input[1]);
+        assert_eq!(hw.to_bits(), down.to_bits());
+        assert_eq!(F128::current_rounding_state(), ::binary128::Rounding::Upward);
+        assert_eq!(down.to_bits() + 1, up.to_bits());
+    }
+
+    #[test]
+    fn threads() {
+        use std::thread;
+        use roundops::rmode::*;
+        use roundops::direction::{Upward, Downward};
+        use binary128::{F128, Rounding};
+
+        let (one, three) = (F128::from(1.), F128::from(3.));
+        let mut c = F128::rmode_controler().unwrap();
+        let down = c.downward();
+        let (up, other) = thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| spawn_with_mode::<F128, Upward, _, _>(scope, |t| t.div(one, three)))
+                .collect::<Vec<_>>();
+            let other = scope.spawn(F128::current_rounding_state).join().unwrap();
+            (handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>(), other)
+        });
+        assert_eq!(other, Rounding::ToNearest);
+        assert_eq!(F128::current_rounding_state(), Rounding::Downward);
+        drop(down);
+        drop(c);
+        let down = with_mode::<F128, Downward, _, _>(|t| t.div(one, three));
+        assert!(up.iter().all(|u| u.to_bits() == down.to_bits() + 1));
+    }
+
+    #[test]
+    fn token() {
+        use roundops::rmode::*;
+        use roundops::RoundDiv;
+        use methods::Hardware;
+        use binary128::F128;
+
+        fn third_up(token: UpwardToken<F128>) -> F128 {
+            token.div(F128::from(1.), F128::from(3.))
+        }
+        fn third_down(token: DownwardToken<F128>) -> F128 {
+            token.div(F128::from(1.), F128::from(3.))
+        }
+
+        let mut c = F128::rmode_controler().unwrap();
+        let up = {
+            let guard = c.upward();
+            third_up(guard.token())
+        };
+        let down = third_down(c.downward().token());
+        assert_eq!(down.to_bits() + 1, up.to_bits());
+        let guard = c.upward();
+        let token = guard.token();
+        let hw = Hardware::<F128>::div_down(F128::from(1.), F128::from(3.));
+        assert_eq!((hw.to_bits(), third_up(token).to_bits()), (down.to_bits(), up.to_bits()));
+        assert!(token.sqrt(F128::from(2.)) * token.sqrt(F128::from(2.)) > F128::from(2.));
+        assert!(token.sub(F128::from(0.), token.mul(F128::from(0.1), F128::from(0.1))) <
+                token.add(F128::from(0.), F128::from(-0.01)));
+    }
+}
+
+pub trait RoundingMethod {
+    type HostMethod: rmode::NativeRoundingMode;
+    type Num;
+}
+
+pub trait RoundAdd: RoundingMethod {
+    fn add_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+    fn add_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+
+    /// Result rounded toward zero, derived from the directed ones.
+    #[inline]
+    fn add_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::add_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { down } else { Self::add_up(lhs, rhs) }
+    }
+
+    /// Result rounded away from zero, derived from the directed ones.
+    #[inline]
+    fn add_away_from_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::add_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { Self::add_up(lhs, rhs) } else { down }
+    }
+
+    /// Result rounded to nearest, which `DefaultRounding` methods get natively.
+    #[inline]
+    fn add_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: Add<Output = Self::Num>
+    {
+        lhs + rhs
+    }
+}
+
+pub trait RoundSub: RoundingMethod {
+    fn sub_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+    fn sub_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+
+    #[inline]
+    fn sub_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::sub_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { down } else { Self::sub_up(lhs, rhs) }
+    }
+
+    #[inline]
+    fn sub_away_from_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::sub_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { Self::sub_up(lhs, rhs) } else { down }
+    }
+
+    #[inline]
+    fn sub_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: Sub<Output = Self::Num>
+    {
+        lhs - rhs
+    }
+}
+
+pub trait RoundMul: RoundingMethod {
+    fn mul_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+    fn mul_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+
+    #[inline]
+    fn mul_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::mul_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { down } else { Self::mul_up(lhs, rhs) }
+    }
+
+    #[inline]
+    fn mul_away_from_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::mul_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { Self::mul_up(lhs, rhs) } else { down }
+    }
+
+    #[inline]
+    fn mul_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: Mul<Output = Self::Num>
+    {
+        lhs * rhs
+    }
+}
+
+pub trait RoundDiv: RoundingMethod {
+    fn div_up(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+    fn div_down(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+
+    #[inline]
+    fn div_toward_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::div_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { down } else { Self::div_up(lhs, rhs) }
+    }
+
+    #[inline]
+    fn div_away_from_zero(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: PartialOrd + Zero + Clone
+    {
+        let down = Self::div_down(lhs.clone(), rhs.clone());
+        if down >= Self::Num::zero() { Self::div_up(lhs, rhs) } else { down }
+    }
+
+    #[inline]
+    fn div_nearest(lhs: Self::Num, rhs: Self::Num) -> Self::Num
+        where Self::Num: Div<Output = Self::Num>
+    {
+        lhs / rhs
+    }
+}
+
+pub trait RoundSqrt: RoundMul {
+    fn sqrt_up(n: Self::Num) -> Self::Num;
+    fn sqrt_down(n: Self::Num) -> Self::Num;
+
+    #[inline]
+    fn sqrt_toward_zero(n: Self::Num) -> Self::Num {
+        Self::sqrt_down(n)
+    }
+
+    #[inline]
+    fn sqrt_away_from_zero(n: Self::Num) -> Self::Num {
+        Self::sqrt_up(n)
+    }
+
+    #[inline]
+    fn sqrt_nearest(n: Self::Num) -> Self::Num
+        where Self::Num: Sqrt<Output = Self::Num>
+    {
+        n.sqrt()
+    }
+}
+
+
+pub trait RoundOps<T: Add + Sub + Mul + Div>
+    : RoundAdd<Num = T> + RoundSub<Num = T> + RoundMul<Num = T> + RoundDiv<Num = T>
+    {
+}
+
+impl<S, T> RoundOps<T> for S
+    where S: RoundAdd<Num = T> + RoundSub<Num = T> + RoundMul<Num = T> + RoundDiv<Num = T>,
+          T: Add + Sub + Mul + Div
+{
+}
+
+/// Addition rounded in the direction `Dir`, implemented for every method.
+pub trait RoundAddDir<Dir: direction::Direction>: RoundingMethod {
+    fn add(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+}
+
+/// Subtraction rounded in the direction `Dir`, implemented for every method.
+pub trait RoundSubDir<Dir: direction::Direction>: RoundingMethod {
+    fn sub(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+}
+
+/// Multiplication rounded in the direction `Dir`, implemented for every method.
+pub trait RoundMulDir<Dir: direction::Direction>: RoundingMethod {
+    fn mul(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+}
+
+/// Division rounded in the direction `Dir`, implemented for every method.
+pub trait RoundDivDir<Dir: direction::Direction>: RoundingMethod {
+    fn div(lhs: Self::Num, rhs: Self::Num) -> Self::Num;
+}
+
+/// Square root rounded in the direction `Dir`, implemented for every method with
+/// `RoundSqrt`.
+pub trait RoundSqrtDir<Dir: direction::Direction>: RoundingMethod {
+    fn sqrt(n: Self::Num) -> Self::Num;
+}
+
+/// The four operations rounded in the direction `Dir`, so that code can be written once
+/// for all directions. Each operator of `RoundedNum` only needs its own trait.
+pub trait RoundArith<Dir: direction::Direction>
+    : RoundAddDir<Dir> + RoundSubDir<Dir> + RoundMulDir<Dir> + RoundDivDir<Dir>
+    {
+}
+
+impl<Dir, M> RoundArith<Dir> for M
+    where Dir: direction::Direction,
+          M: RoundAddDir<Dir> + RoundSubDir<Dir> + RoundMulDir<Dir> + RoundDivDir<Dir>
+{
+}
+
+macro_rules! impl_round_dir {
+    ($tr:ident, $base:ident, $dir:ty, $fn:ident, $op:ident($($arg:ident),+) $(, $bound:path)*) => (
+        impl<M> $tr<$dir> for M
+            where M: $base,
+                  M::Num: Sized $(+ $bound)*
+        {
+            #[inline(always)]
+            fn $fn($($arg: M::Num),+) -> M::Num {
+                M::$op($($arg),+)
+            }
+        }
+    )
+}
+
+macro_rules! impl_round_arith {
+    ($dir:ty, [$add:ident, $sub:ident, $mul:ident, $div:ident, $sqrt:ident] $(, $bound:path)*) => (
+        impl_round_dir!(RoundAddDir, RoundAdd, $dir, add, $add(lhs, rhs) $(, $bound)*);
+        impl_round_dir!(RoundSubDir, RoundSub, $dir, sub, $sub(lhs, rhs) $(, $bound)*);
+        impl_round_dir!(RoundMulDir, RoundMul, $dir, mul, $mul(lhs, rhs) $(, $bound)*);
+        impl_round_dir!(RoundDivDir, RoundDiv, $dir, div, $div(lhs, rhs) $(, $bound)*);
+        impl_round_dir!(RoundSqrtDir, RoundSqrt, $dir, sqrt, $sqrt(n) $(, $bound)*);
+    );
+    (@nearest) => (
+        impl_round_dir!(RoundAddDir, RoundAdd, direction::ToNearest, add, add_nearest(lhs, rhs),
+                        Add<Output = M::Num>);
+        impl_round_dir!(RoundSubDir, RoundSub, direction::ToNearest, sub, sub_nearest(lhs, rhs),
+                        Sub<Output = M::Num>);
+        impl_round_dir!(RoundMulDir, RoundMul, direction::ToNearest, mul, mul_nearest(lhs, rhs),
+                        Mul<Output = M::Num>);
+        impl_round_dir!(RoundDivDir, RoundDiv, direction::ToNearest, div, div_nearest(lhs, rhs),
+                        Div<Output = M::Num>);
+        impl_round_dir!(RoundSqrtDir, RoundSqrt, direction::ToNearest, sqrt, sqrt_nearest(n),
+                        Sqrt<Output = M::Num>);
+    )
+}
+
+impl_round_arith!(direction::Upward, [add_up, sub_up, mul_up, div_up, sqrt_up]);
+impl_round_arith!(direction::Downward, [add_down, sub_down, mul_down, div_down, sqrt_down]);
+impl_round_arith!(direction::TowardZero,
+                  [add_toward_zero, sub_toward_zero, mul_toward_zero, div_toward_zero,
+                   sqrt_toward_zero],
+                  PartialOrd, Zero, Clone);
+impl_round_arith!(direction::AwayFromZero,
+                  [add_away_from_zero, sub_away_from_zero, mul_away_from_zero,
+                   div_away_from_zero, sqrt_away_from_zero],
+                  PartialOrd, Zero, Clone);
+impl_round_arith!(@nearest);
+
+pub mod direction {
+    pub trait Direction: Clone {
+        type Inversed: Direction;
+    }
+
+    #[derive(Clone)]
+    pub enum Upward {}
+
+    impl Direction for Upward {
+        type Inversed = Downward;
+    }
+
+    #[derive(Clone)]
+    pub enum Downward {}
+
+    impl Direction for Downward {
+        type Inversed = Upward;
+    }
+
+    // Rounding symmetric about zero commutes with negation.
+
+    #[derive(Clone)]
+    pub enum TowardZero {}
+
+    impl Direction for TowardZero {
+        type Inversed = TowardZero;
+    }
+
+    #[derive(Clone)]
+    pub enum AwayFromZero {}
+
+    impl Direction for AwayFromZero {
+        type Inversed = AwayFromZero;
+    }
+
+    #[derive(Clone)]
+    pub enum ToNearest {}
+
+    impl Direction for ToNearest {
+        type Inversed = ToNearest;
+    }
+}
+
+/// A number whose operators are rounded in the direction `Dir` by `Method`.
+///
+/// The type records how operations are rounded, not that the value is a bound. A
+/// result is an upper (lower) bound when the expression only grows with its rounded
+/// operands, as sums do. Negation and subtracting a bound of the opposite direction
+/// keep track of the sign, so `up - down` is again an upper bound.
+///
+/// `Sub` between two `Upward` (or two `Downward`) numbers is kept, rounded in the same
+/// direction, because `num_traits::Num` requires `Sub<Output = Self>` and generic code
+/// needs it. Its result is not a bound of the exact difference: subtract a number of the
+/// inverse direction when one is needed.
+///
+/// ```
+/// use roundops::RoundedNum;
+/// use roundops::direction::{Upward, Downward};
+/// use roundops::methods::EmulationRegular;
+///
+/// type Up = RoundedNum<Upward, f64, EmulationRegular<f64>>;
+/// type Down = RoundedNum<Downward, f64, EmulationRegular<f64>>;
+/// // Bounds of 0.1 / 3 and 0.2 / 3.
+/// let (a, b) = (Down::new(0.1) / 3., Up::new(0.2) / 3.);
+/// // An upper bound of 0.2 / 3 - 0.1 / 3.
+/// let width: Up = b - a;
+/// assert!(width.extract() >= b.extract() - a.extract());
+/// ```
+pub struct RoundedNum<Dir: direction::Direction, Num, Method>(Num, PhantomData<(Dir, Method)>);
+impl<Dir: direction::Direction, Num, Method> RoundedNum<Dir, Num, Method> {
+    #[inline(always)]
+    pub fn new(num: Num) -> Self {
+        RoundedNum(num, PhantomData)
+    }
+    #[inline(always)]
+    pub fn extract(self) -> Num {
+        self.0
+    }
+}
+
+// The negation of a bound in one direction is a bound in the opposite one.
+impl<D: direction::Direction, N: Neg<Output = N>, M> Neg for RoundedNum<D, N, M> {
+    type Output = RoundedNum<D::Inversed, N, M>;
+    fn neg(self) -> RoundedNum<D::Inversed, N, M> {
+        RoundedNum(-self.0, PhantomData)
+    }
+}
+
+impl<D: direction::Direction, N: Clone, M> Clone for RoundedNum<D, N, M> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        RoundedNum(self.0.clone(), PhantomData)
+    }
+}
+
+impl<D: direction::Direction, N: Copy, M> Copy for RoundedNum<D, N, M> {}
+
+impl<D: direction::Direction, N: PartialEq, M> PartialEq for RoundedNum<D, N, M> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<D: direction::Direction, N: PartialOrd, M> PartialOrd for RoundedNum<D, N, M> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<D: direction::Direction, N: fmt::Debug, M> fmt::Debug for RoundedNum<D, N, M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("RoundedNum").field(&self.0).finish()
+    }
+}
+
+impl<D: direction::Direction, N: Default, M> Default for RoundedNum<D, N, M> {
+    #[inline]
+    fn default() -> Self {
+        RoundedNum(N::default(), PhantomData)
+    }
+}
+
+impl<D: direction::Direction, N, M> From<N> for RoundedNum<D, N, M> {
+    #[inline(always)]
+    fn from(num: N) -> Self {
+        RoundedNum(num, PhantomData)
+    }
+}
+
+// Operators on owned values, references and raw numbers, and the assignments, all
+// rounded in the direction `D`.
+macro_rules! impl_rnum_binop {
+    ($op:ident, $fn:ident, $aop:ident, $afn:ident, $tr:ident) => (
+        impl<D, N, M> $op for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  M: $tr<D, Num = N>
+        {
+            type Output = RoundedNum<D, N, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: RoundedNum<D, N, M>) -> RoundedNum<D, N, M> {
+                RoundedNum(<M as $tr<D>>::$fn(self.0, rhs.0), PhantomData)
+            }
+        }
+
+        impl<D, N, M> $op<N> for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  M: $tr<D, Num = N>
+        {
+            type Output = RoundedNum<D, N, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: N) -> RoundedNum<D, N, M> {
+                RoundedNum(<M as $tr<D>>::$fn(self.0, rhs), PhantomData)
+            }
+        }
+
+        impl<'a, D, N, M> $op<&'a RoundedNum<D, N, M>> for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            type Output = RoundedNum<D, N, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: &'a RoundedNum<D, N, M>) -> RoundedNum<D, N, M> {
+                $op::$fn(self, rhs.clone())
+            }
+        }
+
+        impl<'a, D, N, M> $op<RoundedNum<D, N, M>> for &'a RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            type Output = RoundedNum<D, N, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: RoundedNum<D, N, M>) -> RoundedNum<D, N, M> {
+                $op::$fn(self.clone(), rhs)
+            }
+        }
+
+        impl<'a, 'b, D, N, M> $op<&'b RoundedNum<D, N, M>> for &'a RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            type Output = RoundedNum<D, N, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: &'b RoundedNum<D, N, M>) -> RoundedNum<D, N, M> {
+                $op::$fn(self.clone(), rhs.clone())
+            }
+        }
+
+        impl<D, N, M> $aop for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            #[inline(always)]
+            fn $afn(&mut self, rhs: RoundedNum<D, N, M>) {
+                self.0 = <M as $tr<D>>::$fn(self.0.clone(), rhs.0);
+            }
+        }
+
+        impl<'a, D, N, M> $aop<&'a RoundedNum<D, N, M>> for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            #[inline(always)]
+            fn $afn(&mut self, rhs: &'a RoundedNum<D, N, M>) {
+                self.0 = <M as $tr<D>>::$fn(self.0.clone(), rhs.0.clone());
+            }
+        }
+
+        impl<D, N, M> $aop<N> for RoundedNum<D, N, M>
+            where D: direction::Direction,
+                  N: Clone,
+                  M: $tr<D, Num = N>
+        {
+            #[inline(always)]
+            fn $afn(&mut self, rhs: N) {
+                self.0 = <M as $tr<D>>::$fn(self.0.clone(), rhs);
+            }
+        }
+    )
+}
+
+impl_rnum_binop!(Add, add, AddAssign, add_assign, RoundAddDir);
+impl_rnum_binop!(Sub, sub, SubAssign, sub_assign, RoundSubDir);
+impl_rnum_binop!(Mul, mul, MulAssign, mul_assign, RoundMulDir);
+impl_rnum_binop!(Div, div, DivAssign, div_assign, RoundDivDir);
+
+// Raw floats on the left-hand side, which coherence only allows for concrete types.
+macro_rules! impl_rnum_lhs {
+    ($fxx:ty, $($op:ident, $fn:ident, $tr:ident);+) => ($(
+        impl<D, M> $op<RoundedNum<D, $fxx, M>> for $fxx
+            where D: direction::Direction,
+                  M: $tr<D, Num = $fxx>
+        {
+            type Output = RoundedNum<D, $fxx, M>;
+            #[inline(always)]
+            fn $fn(self, rhs: RoundedNum<D, $fxx, M>) -> RoundedNum<D, $fxx, M> {
+                RoundedNum(<M as $tr<D>>::$fn(self, rhs.0), PhantomData)
+            }
+        }
+    )+)
+}
+
+impl_rnum_lhs!(f64, Add, add, RoundAddDir; Sub, sub, RoundSubDir; Mul, mul, RoundMulDir;
+               Div, div, RoundDivDir);
+impl_rnum_lhs!(f32, Add, add, RoundAddDir; Sub, sub, RoundSubDir; Mul, mul, RoundMulDir;
+               Div, div, RoundDivDir);
+
+impl<D, N, M> Sum for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: Zero,
+          M: RoundAddDir<D, Num = N>
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(RoundedNum(N::zero(), PhantomData), |acc, x| acc + x)
+    }
+}
+
+impl<'a, D, N, M> Sum<&'a RoundedNum<D, N, M>> for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: Zero + Clone,
+          M: RoundAddDir<D, Num = N>
+{
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(RoundedNum(N::zero(), PhantomData), |acc, x| acc + x)
+    }
+}
+
+impl<D, N, M> Product for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: One,
+          M: RoundMulDir<D, Num = N>
+{
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(RoundedNum(N::one(), PhantomData), |acc, x| acc * x)
+    }
+}
+
+impl<'a, D, N, M> Product<&'a RoundedNum<D, N, M>> for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: One + Clone,
+          M: RoundMulDir<D, Num = N>
+{
+    fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(RoundedNum(N::one(), PhantomData), |acc, x| acc * x)
+    }
+}
+
+// An upper bound minus a lower bound is an upper bound, and vice versa. Directions
+// symmetric about zero are their own inverses and already covered above. The
+// same-direction `Sub` above stays for `Num`, but does not give bounds (see `RoundedNum`).
+macro_rules! impl_rnum_mixed_sub {
+    ($dir:ty, $inv:ty) => (
+        impl<N, M> Sub<RoundedNum<$inv, N, M>> for RoundedNum<$dir, N, M>
+            where M: RoundSubDir<$dir, Num = N>
+        {
+            type Output = RoundedNum<$dir, N, M>;
+            #[inline(always)]
+            fn sub(self, rhs: RoundedNum<$inv, N, M>) -> RoundedNum<$dir, N, M> {
+                RoundedNum(<M as RoundSubDir<$dir>>::sub(self.0, rhs.0), PhantomData)
+            }
+        }
+    )
+}
+
+impl_rnum_mixed_sub!(direction::Upward, direction::Downward);
+impl_rnum_mixed_sub!(direction::Downward, direction::Upward);
+
+impl<D, N, M> RoundedNum<D, N, M>
+    where D: direction::Direction,
+          M: RoundSqrtDir<D, Num = N>
+{
+    #[inline(always)]
+    pub fn sqrt(self) -> RoundedNum<D, N, M> {
+        RoundedNum(<M as RoundSqrtDir<D>>::sqrt(self.0), PhantomData)
+    }
+}
+
+// The remainder of floating-point numbers is exact, so it needs no rounding.
+impl<D: direction::Direction, N: Rem<Output = N>, M> Rem for RoundedNum<D, N, M> {
+    type Output = RoundedNum<D, N, M>;
+    #[inline(always)]
+    fn rem(self, rhs: RoundedNum<D, N, M>) -> RoundedNum<D, N, M> {
+        RoundedNum(self.0 % rhs.0, PhantomData)
+    }
+}
+
+impl<D, N, M> Zero for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: Zero,
+          M: RoundAddDir<D, Num = N>
+{
+    #[inline]
+    fn zero() -> Self {
+        RoundedNum(N::zero(), PhantomData)
+    }
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl<D, N, M> One for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: One,
+          M: RoundMulDir<D, Num = N>
+{
+    #[inline]
+    fn one() -> Self {
+        RoundedNum(N::one(), PhantomData)
+    }
+}
+
+/// Parsing rounds as `N` does, usually to nearest, so a literal that is not
+/// representable has to be bounded separately.
+impl<D, N, M> Num for RoundedNum<D, N, M>
+    where D: direction::Direction,
+          N: Num,
+          M: RoundArith<D, Num = N>
+{
+    type FromStrRadixErr = N::FromStrRadixErr;
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, N::FromStrRadixErr> {
+        N::from_str_radix(s, radix).map(RoundedNum::new)
+    }
+}
+
+/// Only directions commuting with negation: `Signed` requires `Neg<Output = Self>`, but
+/// `-x` of a bound in the direction `D` is a bound in `D::Inversed`.
+impl<D, N, M> Signed for RoundedNum<D, N, M>
+    where D: direction::Direction<Inversed = D>,
+          N: Signed + PartialOrd + Clone,
+          M: RoundArith<D, Num = N>
+{
+    #[inline]
+    fn abs(&self) -> Self {
+        RoundedNum(self.0.abs(), PhantomData)
+    }
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self.0 <= other.0 {
+            Self::zero()
+        } else {
+            RoundedNum(<M as RoundSubDir<D>>::sub(self.0.clone(), other.0.clone()), PhantomData)
+        }
+    }
+    #[inline]
+    fn signum(&self) -> Self {
+        RoundedNum(self.0.signum(), PhantomData)
+    }
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.0.is_positive()
+    }
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+}
+
+impl<D: direction::Direction, N: Bounded, M> Bounded for RoundedNum<D, N, M> {
+    #[inline]
+    fn min_value() -> Self {
+        RoundedNum(N::min_value(), PhantomData)
+    }
+    #[inline]
+    fn max_value() -> Self {
+        RoundedNum(N::max_value(), PhantomData)
+    }
+}
+
+impl<D: direction::Direction, N: ToPrimitive, M> ToPrimitive for RoundedNum<D, N, M> {
+    #[inline]
+    fn to_i64(&self) -> Option<i64> {
+        self.0.to_i64()
+    }
+    #[inline]
+    fn to_u64(&self) -> Option<u64> {
+        self.0.to_u64()
+    }
+    #[inline]
+    fn to_f64(&self) -> Option<f64> {
+        self.0.to_f64()
+    }
+}
+
+/// Only rounding to nearest, as the conversions of `N` round to nearest.
+impl<N, M> NumCast for RoundedNum<direction::ToNearest, N, M>
+    where N: NumCast
+{
+    #[inline]
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        <N as NumCast>::from(n).map(RoundedNum::new)
+    }
+}
+
+// Constants, unary functions, predicates and binary functions of `Float`.
+macro_rules! forward_float {
+    ([$($fn:ident),*], [$($ufn:ident),*], [$($pfn:ident),*], [$($bfn:ident),*]) => (
+        $(
+            #[inline]
+            fn $fn() -> Self {
+                RoundedNum(N::$fn(), PhantomData)
+            }
+        )*
+        $(
+            #[inline]
+            fn $ufn(self) -> Self {
+                RoundedNum(self.0.$ufn(), PhantomData)
+            }
+        )*
+        $(
+            #[inline]
+            fn $pfn(self) -> bool {
+                self.0.$pfn()
+            }
+        )*
+        $(
+            #[inline]
+            fn $bfn(self, other: Self) -> Self {
+                RoundedNum(self.0.$bfn(other.0), PhantomData)
+            }
+        )*
+    )
+}
+
+/// Only rounding to nearest, which also gives `num_traits::real::Real`. Like `Signed`,
+/// `Float` requires `Neg<Output = Self>`, so functions bounded on it cannot take directed
+/// bounds:
+///
+/// ```compile_fail
+/// extern crate num_traits;
+/// extern crate roundops;
+/// use num_traits::Float;
+/// use roundops::RoundedNum;
+/// use roundops::direction::Upward;
+/// use roundops::methods::EmulationRegular;
+///
+/// fn norm<T: Float>(x: T, y: T) -> T {
+///     (x * x + y * y).sqrt()
+/// }
+/// # fn main() {
+/// let x = RoundedNum::<Upward, f64, EmulationRegular<f64>>::new(0.3);
+/// norm(x, x);
+/// # }
+/// ```
+///
+/// `sqrt`, `recip` and `abs_sub` round through the method like the operators. The other
+/// functions are the ones of `N` in the current rounding mode, which need not be
+/// correctly rounded.
+impl<N, M> Float for RoundedNum<direction::ToNearest, N, M>
+    where N: Float,
+          M: RoundArith<direction::ToNearest, Num = N> + RoundSqrtDir<direction::ToNearest>
+{
+    forward_float!([nan, infinity, neg_infinity, neg_zero, min_value, min_positive_value,
+                    max_value],
+                   [floor, ceil, round, trunc, fract, abs, signum, exp, exp2, ln, log2, log10,
+                    cbrt, sin, cos, tan, asin, acos, atan, exp_m1, ln_1p, sinh, cosh, tanh,
+                    asinh, acosh, atanh],
+                   [is_nan, is_infinite, is_finite, is_normal, is_sign_positive,
+                    is_sign_negative],
+                   [powf, log, max, min, hypot, atan2]);
+    #[inline]
+    fn sqrt(self) -> Self {
+        RoundedNum(<M as RoundSqrtDir<direction::ToNearest>>::sqrt(self.0), PhantomData)
+    }
+    #[inline]
+    fn recip(self) -> Self {
+        RoundedNum(<M as RoundDivDir<direction::ToNearest>>::div(N::one(), self.0), PhantomData)
+    }
+    #[inline]
+    fn abs_sub(self, other: Self) -> Self {
+        if self.0 <= other.0 {
+            Self::zero()
+        } else {
+            RoundedNum(<M as RoundSubDir<direction::ToNearest>>::sub(self.0, other.0), PhantomData)
+        }
+    }
+    #[inline]
+    fn classify(self) -> FpCategory {
+        self.0.classify()
+    }
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        RoundedNum(self.0.mul_add(a.0, b.0), PhantomData)
+    }
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        RoundedNum(self.0.powi(n), PhantomData)
+    }
+    #[inline]
+    fn sin_cos(self) -> (Self, Self) {
+        let (s, c) = self.0.sin_cos();
+        (RoundedNum(s, PhantomData), RoundedNum(c, PhantomData))
+    }
+    #[inline]
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.0.integer_decode()
+    }
+}
+
+#[macro_export]
+macro_rules! rnum_init {
+    (<$dir:path,$n:ty,$m:ty>,($numf:expr $(, $num:expr)+)) => (
+        (RoundedNum::<$dir, $n, $m>::new($numf) $(, RoundedNum::<$dir, $n, $m>::new($num))+)
+    )
+}
+
+/// Values that `RoundedSession::compute` passes to its closure as `RoundedNum`s.
+///
+/// Numbers, tuples of inputs, and slices, arrays and `Vec`s of the numbers of a method
+/// are converted element by element.
+pub trait SessionInput<Dir: direction::Direction, M> {
+    type Rounded;
+    fn into_rounded(self) -> Self::Rounded;
+}
+
+/// Values that `RoundedSession::compute` returns from its closure, with every
+/// `RoundedNum` replaced by the number it holds.
+pub trait SessionOutput {
+    type Plain;
+    fn into_plain(self) -> Self::Plain;
+}
+
+/// Implements `SessionInput` and `SessionOutput` for number types, which sessions take as
+/// `RoundedNum`s and return as they are. Generic types list their parameters first.
+///
+/// ```
+/// #[macro_use]
+/// extern crate roundops;
+///
+/// #[derive(Clone, Copy)]
+/// struct Fixed<T>(T);
+/// impl_session_scalar!(<T> Fixed<T>);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! impl_session_scalar {
+    (<$($g:ident),+> $t:ty) => (
+        impl<$($g,)+ Dir: $crate::direction::Direction, M> $crate::SessionInput<Dir, M> for $t {
+            type Rounded = $crate::RoundedNum<Dir, $t, M>;
+            #[inline(always)]
+            fn into_rounded(self) -> Self::Rounded {
+                $crate::RoundedNum::new(self)
+            }
+        }
+
+        impl<$($g),+> $crate::SessionOutput for $t {
+            type Plain = $t;
+            #[inline(always)]
+            fn into_plain(self) -> $t {
+                self
+            }
+        }
+    );
+    ($($t:ty),*) => ($(
+        impl<Dir: $crate::direction::Direction, M> $crate::SessionInput<Dir, M> for $t {
+            type Rounded = $crate::RoundedNum<Dir, $t, M>;
+            #[inline(always)]
+            fn into_rounded(self) -> Self::Rounded {
+                $crate::RoundedNum::new(self)
+            }
+        }
+
+        impl $crate::SessionOutput for $t {
+            type Plain = $t;
+            #[inline(always)]
+            fn into_plain(self) -> $t {
+                self
+            }
+        }
+    )*)
+}
+impl_session_scalar!(f32, f64);
+
+macro_rules! impl_session_plain {
+    ($($t:ty),*) => ($(
+        impl SessionOutput for $t {
+            type Plain = $t;
+            #[inline(always)]
+            fn into_plain(self) -> $t {
+                self
+            }
+        }
+    )*)
+}
+impl_session_plain!((), bool, Ordering, usize, isize, u32, u64, i32, i64);
+
+impl<D: direction::Direction, N, M> SessionOutput for RoundedNum<D, N, M> {
+    type Plain = N;
+    #[inline(always)]
+    fn into_plain(self) -> N {
+        self.0
+    }
+}
+
+impl<D: direction::Direction, N: Clone, M> SessionInput<D, M> for &[N] {
+    type Rounded = Vec<RoundedNum<D, N, M>>;
+    #[inline]
+    fn into_rounded(self) -> Self::Rounded {
+        self.iter().cloned().map(RoundedNum::new).collect()
+    }
+}
+
+impl<D: direction::Direction, N, M> SessionInput<D, M> for Vec<N> {
+    type Rounded = Vec<RoundedNum<D, N, M>>;
+    #[inline]
+    fn into_rounded(self) -> Self::Rounded {
+        self.into_iter().map(RoundedNum::new).collect()
+    }
+}
+
+impl<T: SessionOutput> SessionOutput for Vec<T> {
+    type Plain = Vec<T::Plain>;
+    #[inline]
+    fn into_plain(self) -> Self::Plain {
+        self.into_iter().map(T::into_plain).collect()
+    }
+}
+
+impl<D: direction::Direction, N, M, const K: usize> SessionInput<D, M> for [N; K] {
+    type Rounded = [RoundedNum<D, N, M>; K];
+    #[inline]
+    fn into_rounded(self) -> Self::Rounded {
+        self.map(RoundedNum::new)
+    }
+}
+
+impl<T: SessionOutput, const K: usize> SessionOutput for [T; K] {
+    type Plain = [T::Plain; K];
+    #[inline]
+    fn into_plain(self) -> Self::Plain {
+        self.map(T::into_plain)
+    }
+}
+
+impl<T: SessionOutput> SessionOutput for Option<T> {
+    type Plain = Option<T::Plain>;
+    #[inline]
+    fn into_plain(self) -> Self::Plain {
+        self.map(T::into_plain)
+    }
+}
+
+macro_rules! impl_session_tuple {
+    ($(($($t:ident),+)),*) => ($(
+        impl<Dir: direction::Direction, M, $($t: SessionInput<Dir, M>),+> SessionInput<Dir, M>
+            for ($($t,)+)
+        {
+            type Rounded = ($($t::Rounded,)+);
+            #[allow(non_snake_case)]
+            #[inline]
+            fn into_rounded(self) -> Self::Rounded {
+                let ($($t,)+) = self;
+                ($($t.into_rounded(),)+)
+            }
+        }
+
+        impl<$($t: SessionOutput),+> SessionOutput for ($($t,)+) {
+            type Plain = ($($t::Plain,)+);
+            #[allow(non_snake_case)]
+            #[inline]
+            fn into_plain(self) -> Self::Plain {
+                let ($($t,)+) = self;
+                ($($t.into_plain(),)+)
+            }
+        }
+    )*)
+}
+impl_session_tuple!((A), (A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E), (A, B, C, D, E, F));
+
+/// A computation on `RoundedNum`s written once for every direction, evaluated by
+/// `RoundedSession::bounds` on the plain input `I`.
+pub trait DirectedFn<M: RoundingMethod, I> {
+    type Output<D: direction::Direction>: SessionOutput;
+    fn call<D: direction::Direction>(&self, input: I) -> Self::Output<D> where M: RoundArith<D>;
+}
+
+/// Evaluates a closure once downward and once upward with `RoundedSession::compute`,
+/// giving `(lower, upper)`.
+///
+/// The closure must be written inline: its body is expanded once for each direction, so
+/// that it is checked with both `RoundedNum<Downward, ..>` and `RoundedNum<Upward, ..>`
+/// arguments. Side effects of the body happen twice, and each direction is a separate
+/// `compute`, which for `HardwareSession` switches the mode twice. A computation held in
+/// a value should implement `DirectedFn` and go through `RoundedSession::bounds`. See
+/// there for when the results are bounds.
+///
+/// ```
+/// #[macro_use]
+/// extern crate roundops;
+/// use roundops::methods::EmulationRegular;
+///
+/// # fn main() {
+/// let (lo, hi) = bounds!(EmulationRegular<f64>, [1., 3.], |[a, b]| a / b);
+/// assert!(lo < hi && hi - lo == f64::EPSILON / 4.);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate roundops;
+/// use roundops::methods::EmulationRegular;
+///
+/// # fn main() {
+/// let third = |x| x / 3.;
+/// let (lo, hi) = bounds!(EmulationRegular<f64>, 1., third);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! bounds {
+    ($m:ty, $input:expr, move |$arg:pat| $body:expr) => ({
+        let input = $input;
+        (<$m as $crate::RoundedSession>::compute::<$crate::direction::Downward, _, _, _>(
+            input.clone(), move |$arg| $body),
+         <$m as $crate::RoundedSession>::compute::<$crate::direction::Upward, _, _, _>(
+            input, move |$arg| $body))
+    });
+    ($m:ty, $input:expr, |$arg:pat| $body:expr) => ({
+        let input = $input;
+        (<$m as $crate::RoundedSession>::compute::<$crate::direction::Downward, _, _, _>(
+            input.clone(), |$arg| $body),
+         <$m as $crate::RoundedSession>::compute::<$crate::direction::Upward, _, _, _>(
+            input, |$arg| $body))
+    })
+}
+
+pub trait RoundedSession: Clone + Sized {
+    type Num: Clone;
+
+    /// Runs `func` on `input` converted to `RoundedNum`s of direction `Dir`, and converts
+    /// its result back to plain numbers.
+    ///
+    /// Methods that need a particular floating-point environment override this to set it
+    /// up once for the whole closure.
+    #[inline]
+    fn compute<Dir, I, O, F>(input: I, func: F) -> O::Plain
+        where Dir: direction::Direction,
+              I: SessionInput<Dir, Self>,
+              O: SessionOutput,
+              F: FnOnce(I::Rounded) -> O
+    {
+        func(input.into_rounded()).into_plain()
+    }
+
+    /// Evaluates `func` on `input` rounding downward and then upward, giving `(lower, upper)`.
+    ///
+    /// The results enclose the exact value only when rounding every operation in one
+    /// direction moves the result in that direction, e.g. for sums and quotients of
+    /// positive numbers. Subtracting, dividing by or multiplying with a computed value of
+    /// unknown sign breaks this, and negation of a `RoundedNum` already flips its direction.
+    #[allow(clippy::type_complexity)]
+    #[inline]
+    fn bounds<I, F>(func: F, input: I) -> (<F::Output<direction::Downward> as SessionOutput>::Plain,
+                                          <F::Output<direction::Upward> as SessionOutput>::Plain)
+        where Self: RoundArith<direction::Downward> + RoundArith<direction::Upward>,
+              I: Clone,
+              F: DirectedFn<Self, I>
+    {
+        (func.call::<direction::Downward>(input.clone()).into_plain(),
+         func.call::<direction::Upward>(input).into_plain())
+    }
+
+    /// `compute` that first checks the floating-point environment.
+    #[inline]
+    fn checked_compute<Dir, I, O, F>(input: I, func: F) -> Result<O::Plain, rmode::EnvironmentError>
+        where Dir: direction::Direction,
+              I: SessionInput<Dir, Self>,
+              O: SessionOutput,
+              F: FnOnce(I::Rounded) -> O,
+              Self::Num: IEEE754Float
+    {
+        rmode::check_environment::<Self::Num>()?;
+        Ok(Self::compute(input, func))
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[inline]
+    fn calc_with<Dir: direction::Direction>(input: Vec<Self::Num>,
+                                            func: fn(Vec<RoundedNum<Dir, Self::Num, Self>>)
                                                      -> Vec<RoundedNum<Dir, Self::Num, Self>>)
                                             -> Vec<Self::Num> {
-        func(input
-                 .into_iter()
-                 .map(|num| RoundedNum(num, PhantomData))
-                 .collect::<Vec<_>>())
-                .into_iter()
-                .map(|e| e.0)
-                .collect::<Vec<_>>()
+        Self::compute(input, func)
+    }
+
+    /// `calc_with` that first checks the floating-point environment.
+    #[allow(clippy::type_complexity)]
+    #[inline]
+    fn checked_calc_with<Dir: direction::Direction>(input: Vec<Self::Num>,
+                                                    func: fn(Vec<RoundedNum<Dir, Self::Num, Self>>)
+                                                             -> Vec<RoundedNum<Dir, Self::Num, Self>>)
+                                                    -> Result<Vec<Self::Num>, rmode::EnvironmentError>
+        where Self::Num: IEEE754Float
+    {
+        Self::checked_compute(input, func)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use methods::EmulationRegular;
+
+    type Emuf64 = EmulationRegular<f64>;
+
+    #[test]
+    fn symmetric_directions() {
+        use methods::Hardware;
+        use float_traits::Abs;
+        use roundops::direction::{TowardZero, AwayFromZero, ToNearest};
+        use binary128::F128;
+
+        type Hw = Hardware<F128>;
+        type Emu = EmulationRegular<F128>;
+        type Op = fn(F128, F128) -> F128;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let a = F128::from(rng.gen::<f64>() - 0.5);
+            let b = Emu::div_up(F128::from(rng.gen::<f64>() - 0.5), F128::from(3.));
+            let ops: [(Op, Op, Op, Op, Op); 4] =
+                [(Emu::add_toward_zero, Emu::add_away_from_zero, Hw::add_toward_zero,
+                  Emu::add_up, Emu::add_down),
+                 (Emu::sub_toward_zero, Emu::sub_away_from_zero, Hw::sub_toward_zero,
+                  Emu::sub_up, Emu::sub_down),
+                 (Emu::mul_toward_zero, Emu::mul_away_from_zero, Hw::mul_toward_zero,
+                  Emu::mul_up, Emu::mul_down),
+                 (Emu::div_toward_zero, Emu::div_away_from_zero, Hw::div_toward_zero,
+                  Emu::div_up, Emu::div_down)];
+            for &(tz, az, hw, up, down) in &ops {
+                let (t, z) = (tz(a, b), az(a, b));
+                assert_eq!(t.to_bits(), hw(a, b).to_bits());
+                assert!(t.abs() <= z.abs());
+                assert!((t == up(a, b) && z == down(a, b)) || (t == down(a, b) && z == up(a, b)));
+            }
+            assert_eq!(Emu::sqrt_toward_zero(a.abs()), Hw::sqrt_toward_zero(a.abs()));
+
+            let (x, y) = (RoundedNum::<TowardZero, F128, Emu>::new(a),
+                          RoundedNum::<TowardZero, F128, Emu>::new(b));
+            assert_eq!((x * y).extract(), Emu::mul_toward_zero(a, b));
+            let (x, y) = (RoundedNum::<AwayFromZero, F128, Emu>::new(a),
+                          RoundedNum::<AwayFromZero, F128, Emu>::new(b));
+            assert_eq!((x / y).extract(), Emu::div_away_from_zero(a, b));
+            let (x, y) = (RoundedNum::<ToNearest, F128, Emu>::new(a),
+                          RoundedNum::<ToNearest, F128, Emu>::new(b));
+            assert_eq!((x - y).extract(), a - b);
+        }
+    }
+
+    #[test]
+    fn direction_generic() {
+        use roundops::direction::{Direction, Upward, Downward};
+
+        // Bound of `a * b - c * d` in the direction `D`.
+        fn det<D: Direction, M>(a: f64, b: f64, c: f64, d: f64) -> f64
+            where M: RoundArith<D, Num = f64> + RoundArith<D::Inversed, Num = f64>
+        {
+            <M as RoundSubDir<D>>::sub(<M as RoundMulDir<D>>::mul(a, b),
+                                       <M as RoundMulDir<D::Inversed>>::mul(c, d))
+        }
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b, c, d) = (rng.gen(), rng.gen(), rng.gen(), rng.gen());
+            assert_eq!(det::<Upward, Emuf64>(a, b, c, d),
+                       Emuf64::sub_up(Emuf64::mul_up(a, b), Emuf64::mul_down(c, d)));
+            assert_eq!(det::<Downward, Emuf64>(a, b, c, d),
+                       Emuf64::sub_down(Emuf64::mul_down(a, b), Emuf64::mul_up(c, d)));
+            assert_eq!(<Emuf64 as RoundSqrtDir<Upward>>::sqrt(a), Emuf64::sqrt_up(a));
+        }
+    }
+
+    #[test]
+    fn per_operator() {
+        use roundops::direction::Upward;
+
+        // A method with addition only still gets `+` and `Sum` on `RoundedNum`.
+        #[derive(Clone)]
+        struct AddOnly;
+        impl RoundingMethod for AddOnly {
+            type HostMethod = rmode::DefaultRounding;
+            type Num = f64;
+        }
+        impl RoundAdd for AddOnly {
+            fn add_up(a: f64, b: f64) -> f64 {
+                Emuf64::add_up(a, b)
+            }
+            fn add_down(a: f64, b: f64) -> f64 {
+                Emuf64::add_down(a, b)
+            }
+        }
+        let v = [0.1, 0.2, 0.3];
+        let sum = v.iter()
+            .map(|&x| RoundedNum::<Upward, f64, AddOnly>::new(x))
+            .sum::<RoundedNum<_, _, _>>();
+        assert_eq!(sum.extract(), Emuf64::add_up(Emuf64::add_up(0.1, 0.2), 0.3));
+        assert_eq!((RoundedNum::<Upward, f64, AddOnly>::new(1.) + 1e-300).extract(),
+                   1. + f64::EPSILON);
+    }
+
+    #[test]
+    fn negation() {
+        use roundops::direction::{Upward, Downward};
+
+        type Up = RoundedNum<Upward, f64, Emuf64>;
+        type Down = RoundedNum<Downward, f64, Emuf64>;
+        let mut rng = thread_rng();
+        for _ in 0..10000 {
+            let (a, b, c) = (rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>());
+            let neg: Down = -Up::new(a);
+            assert_eq!(neg.extract(), -a);
+            // Upper bound of `a - b * c` from a lower bound of `b * c`.
+            let r: Up = Up::new(a) - Down::new(b) * Down::new(c);
+            assert_eq!(r.extract(), Emuf64::sub_up(a, Emuf64::mul_down(b, c)));
+            let r: Down = Down::new(a) - (-(Down::new(b) * Down::new(c)));
+            assert_eq!(r.extract(), Emuf64::add_down(a, Emuf64::mul_down(b, c)));
+        }
+    }
+
+    #[allow(clippy::op_ref)]
+    #[test]
+    fn operators() {
+        use roundops::direction::Upward;
+
+        type Up = RoundedNum<Upward, f64, Emuf64>;
+        let mut rng = thread_rng();
+        let v = (0..100).map(|_| rng.gen::<f64>() - 0.5).collect::<Vec<_>>();
+        let r = v.iter().map(|&x| Up::from(x)).collect::<Vec<_>>();
+        let sum = v.iter().fold(0., |acc, &x| Emuf64::add_up(acc, x));
+        let prod = v.iter().fold(1., |acc, &x| Emuf64::mul_up(acc, x));
+        assert_eq!(r.iter().sum::<Up>(), Up::new(sum));
+        assert_eq!(r.iter().cloned().product::<Up>().extract(), prod);
+
+        let (a, b) = (r[0], r[1]);
+        let mut acc = Up::default();
+        acc += a;
+        acc -= &b;
+        acc *= 3.;
+        acc /= b;
+        let expected = Emuf64::div_up(Emuf64::mul_up(Emuf64::sub_up(v[0], v[1]), 3.), v[1]);
+        assert_eq!(acc.extract(), expected);
+        assert_eq!((&a + &b).extract(), Emuf64::add_up(v[0], v[1]));
+        assert_eq!((a - &b).extract(), Emuf64::sub_up(v[0], v[1]));
+        assert_eq!((&a * b).extract(), Emuf64::mul_up(v[0], v[1]));
+        assert_eq!((a / 3.).extract(), Emuf64::div_up(v[0], 3.));
+        assert_eq!((1. - a).extract(), Emuf64::sub_up(1., v[0]));
+        assert!(a + b >= a + b && format!("{:?}", a) == format!("RoundedNum({:?})", v[0]));
+    }
+
+    #[test]
+    fn num_traits() {
+        use num_traits::{Num, Float, Signed, Bounded, NumCast};
+        use roundops::direction::{Upward, Downward, ToNearest, TowardZero};
+
+        // Explicit Euler steps of `y' = -k y`, written only against `Num`.
+        fn euler<T: Num + Copy>(y0: T, k: T, h: T, n: usize) -> T {
+            (0..n).fold(y0, |y, _| y - h * k * y)
+        }
+        fn norm<T: Float>(x: T, y: T) -> T {
+            x.hypot(y).max((x * x + y * y).sqrt())
+        }
+        let (k, h) = (0.7, 0.013);
+        let up = euler(RoundedNum::<Upward, f64, Emuf64>::new(1.), k.into(), h.into(), 100);
+        let down = euler(RoundedNum::<Downward, f64, Emuf64>::new(1.), k.into(), h.into(), 100);
+        let near = euler(1., k, h, 100);
+        assert!(down.extract() < near && near < up.extract());
+        assert_eq!(up.extract(), (0..100).fold(1., |y, _| {
+            Emuf64::sub_up(y, Emuf64::mul_up(Emuf64::mul_up(h, k), y))
+        }));
+
+        let (x, y) = (RoundedNum::<ToNearest, f64, Emuf64>::new(0.3),
+                      <RoundedNum<ToNearest, f64, Emuf64> as NumCast>::from(0.4).unwrap());
+        assert_eq!(norm(x, y).extract(), norm(0.3, 0.4));
+        let t = RoundedNum::<TowardZero, f64, Emuf64>::new(-0.1);
+        assert_eq!(t.abs().extract(), 0.1);
+        assert_eq!(t.abs_sub(&(-t)).extract(), 0.);
+        assert_eq!(RoundedNum::<Upward, f64, Emuf64>::max_value().extract(), f64::MAX);
+        assert_eq!(RoundedNum::<Upward, f64, Emuf64>::from_str_radix("0.5", 10).ok(),
+                   Some(RoundedNum::new(0.5)));
     }
 }
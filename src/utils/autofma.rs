@@ -0,0 +1,87 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use float_traits::IEEE754Float;
+use utils::safeeft::safetwoproduct_branch;
+
+const UNKNOWN: u8 = 0;
+const ABSENT: u8 = 1;
+const PRESENT: u8 = 2;
+
+static FMA: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect() -> bool {
+    is_x86_feature_detected!("fma")
+}
+
+// FMA is part of the base instruction set, and `mul_add` compiles to it.
+#[cfg(target_arch = "aarch64")]
+fn detect() -> bool {
+    true
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn detect() -> bool {
+    false
+}
+
+/// Whether the CPU has a fused multiply-add, detected on the first call.
+#[inline]
+pub fn fma_available() -> bool {
+    match FMA.load(Ordering::Relaxed) {
+        PRESENT => true,
+        ABSENT => false,
+        _ => {
+            let found = detect();
+            FMA.store(if found { PRESENT } else { ABSENT }, Ordering::Relaxed);
+            found
+        }
+    }
+}
+
+/// Floats whose error-free product can use a fused multiply-add found at run time.
+pub trait RuntimeFma: IEEE754Float + Clone {
+    /// `a * b` and its rounding error, using the fused multiply-add.
+    ///
+    /// # Safety
+    ///
+    /// `fma_available()` must be `true`.
+    unsafe fn twoproduct_fma(a: Self, b: Self) -> (Self, Self);
+}
+
+macro_rules! impl_runtime_fma {
+    ($fxx:ident, $fma:ident) => (
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        #[target_feature(enable = "fma")]
+        unsafe fn $fma(a: $fxx, b: $fxx, c: $fxx) -> $fxx {
+            a.mul_add(b, c)
+        }
+
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        #[inline]
+        unsafe fn $fma(a: $fxx, b: $fxx, c: $fxx) -> $fxx {
+            a.mul_add(b, c)
+        }
+
+        impl RuntimeFma for $fxx {
+            #[inline]
+            unsafe fn twoproduct_fma(a: $fxx, b: $fxx) -> ($fxx, $fxx) {
+                let prod = a * b;
+                (prod, $fma(a, b, -prod))
+            }
+        }
+    )
+}
+
+impl_runtime_fma!(f64, fma_f64);
+impl_runtime_fma!(f32, fma_f32);
+
+/// `safetwoproduct` with a fused multiply-add when the CPU has one.
+#[inline]
+pub fn safetwoproduct_auto<T: RuntimeFma>(a: T, b: T) -> (T, T) {
+    if fma_available() {
+        unsafe { T::twoproduct_fma(a, b) }
+    } else {
+        safetwoproduct_branch(a, b)
+    }
+}
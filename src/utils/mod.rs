@@ -4,3 +4,6 @@ pub extern crate fma;
 
 mod succpred;
 pub use self::succpred::{succ,pred,FloatSuccPred};
+
+mod autofma;
+pub use self::autofma::{fma_available, safetwoproduct_auto, RuntimeFma};
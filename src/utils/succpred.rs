@@ -24,7 +24,7 @@ pub trait FloatSuccPred: Abs<Output = Self> + BinaryFloat + Underflow + Clone {
         } else {
             let c = Self::radix() / Self::eps() * self.clone();
             let e = phi * c.clone().abs();
-            (c + e) / (two * Self::eps())
+            (c + e) / (two / Self::eps())
         }
     }
 
@@ -41,7 +41,7 @@ pub trait FloatSuccPred: Abs<Output = Self> + BinaryFloat + Underflow + Clone {
         } else {
             let c = Self::radix() / Self::eps() * self.clone();
             let e = phi * c.clone().abs();
-            (c - e) / (two * Self::eps())
+            (c - e) / (two / Self::eps())
         }
     }
 }
@@ -51,7 +51,6 @@ impl<T: Abs<Output = Self> + BinaryFloat + Underflow + Clone> FloatSuccPred for
 #[cfg(test)]
 mod tests {
     use core::f64;
-    use core::mem::transmute;
     use rand::{Rng, thread_rng};
     use super::FloatSuccPred;
 
@@ -62,13 +61,20 @@ mod tests {
             let f = rng.gen::<f64>();
             let (succ, pred) = (f.succ(), f.pred());
             assert!(succ - f == f64::MIN_POSITIVE * f64::EPSILON ||
-                    unsafe { transmute::<f64, u64>(succ) - transmute::<f64, u64>(f) == 1 } ||
+                    succ.to_bits() - f.to_bits() == 1 ||
                     (f >= f64::MAX && succ == f64::INFINITY) ||
                     f == f64::NEG_INFINITY || f != f);
             assert!(f - pred == f64::MIN_POSITIVE * f64::EPSILON ||
-                    unsafe { transmute::<f64, u64>(f) - transmute::<f64, u64>(pred) == 1 } ||
+                    f.to_bits() - pred.to_bits() == 1 ||
                     (f <= f64::MIN && succ == f64::INFINITY) ||
                     f == f64::INFINITY || f != f);
         }
+        for _ in 0..1000000 {
+            let f = (rng.gen::<f64>() - 0.5) * 2f64.powi(rng.gen_range(-1021, -969));
+            let (succ, pred) = (f.succ(), f.pred());
+            let (up, down) = if f > 0. { (1, -1) } else { (-1, 1) };
+            assert_eq!(succ.to_bits() as i64 - f.to_bits() as i64, up);
+            assert_eq!(pred.to_bits() as i64 - f.to_bits() as i64, down);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,318 @@
+use core::arch::asm;
+use core::cmp::Ordering;
+use core::ops::{Neg, Add, Sub, Mul, Div, Rem};
+
+use num_traits::{Num, Zero, One};
+use float_traits::Sqrt;
+
+use impl_session_scalar;
+use roundops::rmode::EditRoundingMode;
+use flags::{ExceptionFlags, ExceptionStatus};
+
+// Rounding control and precision control fields of the x87 FPU control word
+const RC_MASK: u16 = 0x0C00;
+const RC_NEAREST: u16 = 0x0000;
+const RC_DOWN: u16 = 0x0400;
+const RC_UP: u16 = 0x0800;
+const RC_TOWARD_ZERO: u16 = 0x0C00;
+const PC_MASK: u16 = 0x0300;
+const PC_24: u16 = 0x0000;
+const PC_53: u16 = 0x0200;
+
+#[inline]
+fn get_control_word() -> u16 {
+    let mut cw = 0u16;
+    unsafe { asm!("fnstcw word ptr [{}]", in(reg) &mut cw, options(nostack, preserves_flags)) };
+    cw
+}
+
+#[inline]
+unsafe fn set_control_word(cw: u16) {
+    asm!("fldcw word ptr [{}]", in(reg) &cw, options(nostack, preserves_flags));
+}
+
+#[inline]
+fn get_status_word() -> u16 {
+    let sw: u16;
+    unsafe { asm!("fnstsw ax", out("ax") sw, options(nomem, nostack, preserves_flags)) };
+    sw
+}
+
+#[inline]
+fn clear_exceptions() {
+    unsafe { asm!("fnclex", options(nomem, nostack, preserves_flags)) };
+}
+
+#[inline]
+unsafe fn set_rounding_precision(bits: u16) {
+    set_control_word((get_control_word() & !(RC_MASK | PC_MASK)) | bits);
+}
+
+/// Float whose arithmetic is computed on the x87 FPU.
+///
+/// The precision control is set to the precision of `T` together with the rounding
+/// direction, so a result is rounded only once unless it is subnormal. Directed results
+/// stay correct even then, because rounding twice in the same direction is harmless.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct X87<T>(pub T);
+
+macro_rules! impl_x87_binop {
+    ($fxx:ident, $ptr:expr, $op:ident, $method:ident, $ins:expr) => (
+        impl $op for X87<$fxx> {
+            type Output = Self;
+            fn $method(self, rhs: Self) -> Self {
+                let mut r: $fxx = 0.;
+                unsafe {
+                    asm!(concat!("fld ", $ptr, " ptr [{a}]"),
+                         concat!("fld ", $ptr, " ptr [{b}]"),
+                         concat!($ins, " st(1), st"),
+                         concat!("fstp ", $ptr, " ptr [{r}]"),
+                         a = in(reg) &self.0, b = in(reg) &rhs.0, r = in(reg) &mut r,
+                         out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+                         out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+                         options(nostack))
+                }
+                X87(r)
+            }
+        }
+    )
+}
+
+macro_rules! impl_x87 {
+    ($fxx:ident, $ptr:expr, $pc:expr) => (
+        impl_x87_binop!($fxx, $ptr, Add, add, "faddp");
+        impl_x87_binop!($fxx, $ptr, Sub, sub, "fsubp");
+        impl_x87_binop!($fxx, $ptr, Mul, mul, "fmulp");
+        impl_x87_binop!($fxx, $ptr, Div, div, "fdivp");
+
+        impl Rem for X87<$fxx> {
+            type Output = Self;
+            fn rem(self, rhs: Self) -> Self {
+                X87(self.0 % rhs.0)
+            }
+        }
+
+        impl Neg for X87<$fxx> {
+            type Output = Self;
+            fn neg(self) -> Self {
+                X87(-self.0)
+            }
+        }
+
+        impl Sqrt for X87<$fxx> {
+            type Output = Self;
+            fn sqrt(self) -> Self {
+                let mut r: $fxx = 0.;
+                unsafe {
+                    asm!(concat!("fld ", $ptr, " ptr [{a}]"),
+                         "fsqrt",
+                         concat!("fstp ", $ptr, " ptr [{r}]"),
+                         a = in(reg) &self.0, r = in(reg) &mut r,
+                         out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+                         out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+                         options(nostack))
+                }
+                X87(r)
+            }
+        }
+
+        impl PartialEq for X87<$fxx> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl PartialOrd for X87<$fxx> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        impl From<$fxx> for X87<$fxx> {
+            fn from(x: $fxx) -> Self {
+                X87(x)
+            }
+        }
+
+        impl Zero for X87<$fxx> {
+            fn zero() -> Self {
+                X87(0.)
+            }
+            fn is_zero(&self) -> bool {
+                self.0 == 0.
+            }
+        }
+
+        impl One for X87<$fxx> {
+            fn one() -> Self {
+                X87(1.)
+            }
+        }
+
+        impl Num for X87<$fxx> {
+            type FromStrRadixErr = <$fxx as Num>::FromStrRadixErr;
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                <$fxx as Num>::from_str_radix(s, radix).map(X87)
+            }
+        }
+
+        impl EditRoundingMode for X87<$fxx> {
+            // rounding and precision control bits of the FPU control word
+            type RoundingState = u16;
+
+            #[inline]
+            fn current_rounding_state() -> u16 {
+                get_control_word() & (RC_MASK | PC_MASK)
+            }
+            #[inline]
+            unsafe fn set_rounding_state(state: u16) {
+                set_rounding_precision(state & (RC_MASK | PC_MASK));
+            }
+            #[inline]
+            unsafe fn upward() {
+                set_rounding_precision(RC_UP | $pc);
+            }
+            #[inline]
+            unsafe fn downward() {
+                set_rounding_precision(RC_DOWN | $pc);
+            }
+            #[inline]
+            unsafe fn to_nearest() {
+                set_rounding_precision(RC_NEAREST | $pc);
+            }
+            #[inline]
+            unsafe fn toward_zero() {
+                set_rounding_precision(RC_TOWARD_ZERO | $pc);
+            }
+        }
+
+        impl ExceptionStatus for X87<$fxx> {
+            #[inline]
+            fn exception_flags() -> ExceptionFlags {
+                ExceptionFlags::from_x86(get_status_word() as u32)
+            }
+            #[inline]
+            fn clear_exception_flags() {
+                clear_exceptions()
+            }
+        }
+
+        // Without SSE2, the compiler itself computes `$fxx` on the x87 FPU.
+        #[cfg(all(target_arch = "x86", not(target_feature = "sse2")))]
+        impl ExceptionStatus for $fxx {
+            #[inline]
+            fn exception_flags() -> ExceptionFlags {
+                X87::<$fxx>::exception_flags()
+            }
+            #[inline]
+            fn clear_exception_flags() {
+                X87::<$fxx>::clear_exception_flags()
+            }
+        }
+
+        #[cfg(all(target_arch = "x86", not(target_feature = "sse2")))]
+        impl EditRoundingMode for $fxx {
+            type RoundingState = u16;
+
+            #[inline]
+            fn current_rounding_state() -> u16 {
+                X87::<$fxx>::current_rounding_state()
+            }
+            #[inline]
+            unsafe fn set_rounding_state(state: u16) {
+                X87::<$fxx>::set_rounding_state(state)
+            }
+            #[inline]
+            unsafe fn upward() {
+                X87::<$fxx>::upward()
+            }
+            #[inline]
+            unsafe fn downward() {
+                X87::<$fxx>::downward()
+            }
+            #[inline]
+            unsafe fn to_nearest() {
+                X87::<$fxx>::to_nearest()
+            }
+            #[inline]
+            unsafe fn toward_zero() {
+                X87::<$fxx>::toward_zero()
+            }
+        }
+    )
+}
+
+impl_x87!(f64, "qword", PC_53);
+impl_x87!(f32, "dword", PC_24);
+
+impl_session_scalar!(<T> X87<T>);
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, thread_rng};
+
+    use roundops::*;
+    use roundops::rmode::EditRoundingMode;
+    use methods::{Hardware, EmulationRegular};
+
+    use super::X87;
+
+    macro_rules! check {
+        ($fxx:ident, $n:expr) => ({
+            type Hw = Hardware<X87<$fxx>>;
+            type Emu = EmulationRegular<$fxx>;
+            let mut rng = thread_rng();
+            for _ in 0..$n {
+                let (a, b): ($fxx, $fxx) = (rng.gen(), rng.gen());
+                let scale = (2. as $fxx).powi(rng.gen_range(-60, 60));
+                let (a, b) = (a * scale, b - 0.5);
+                let (x, y) = (X87(a), X87(b));
+                assert_eq!(Hw::add_up(x, y).0, Emu::add_up(a, b));
+                assert_eq!(Hw::add_down(x, y).0, Emu::add_down(a, b));
+                assert_eq!(Hw::sub_up(x, y).0, Emu::sub_up(a, b));
+                assert_eq!(Hw::sub_down(x, y).0, Emu::sub_down(a, b));
+                assert_eq!(Hw::mul_up(x, y).0, Emu::mul_up(a, b));
+                assert_eq!(Hw::mul_down(x, y).0, Emu::mul_down(a, b));
+                assert_eq!(Hw::div_up(x, y).0, Emu::div_up(a, b));
+                assert_eq!(Hw::div_down(x, y).0, Emu::div_down(a, b));
+                assert_eq!(Hw::sqrt_up(x).0, Emu::sqrt_up(a));
+                assert_eq!(Hw::sqrt_down(x).0, Emu::sqrt_down(a));
+            }
+        })
+    }
+
+    #[test]
+    fn directed_f64() {
+        check!(f64, 100000);
+    }
+
+    #[test]
+    fn directed_f32() {
+        check!(f32, 100000);
+    }
+
+    #[test]
+    fn flags() {
+        use flags::{RoundFlags, ExceptionFlags};
+        type Hw = Hardware<X87<f64>>;
+        Hw::clear_flags();
+        assert_eq!(Hw::mul_up(X87(0.5), X87(3.)).0, 1.5);
+        assert!(Hw::take_flags().is_empty());
+        Hw::div_up(X87(1.), X87(3.));
+        assert_eq!(Hw::take_flags(), ExceptionFlags::INEXACT);
+        Hw::div_down(X87(1.), X87(0.));
+        assert_eq!(Hw::take_flags(), ExceptionFlags::DIVIDE_BY_ZERO);
+        Hw::sqrt_up(X87(-1.));
+        assert_eq!(Hw::take_flags(), ExceptionFlags::INVALID);
+    }
+
+    #[test]
+    fn no_double_rounding() {
+        // 1 + 2^-53 + 2^-65 rounds to 1 + 2^-53 in extended precision, then to 1.
+        let (one, b) = (X87(1f64), X87(2f64.powi(-53) + 2f64.powi(-65)));
+        let mut c = X87::<f64>::rmode_controler().unwrap();
+        let r = c.to_nearest_session(|| one + b);
+        assert_eq!(r.0, 1. + 2f64.powi(-52));
+        assert_eq!(r.0, 1. + b.0);
+    }
+}